@@ -1,42 +1,110 @@
 mod activity;
+mod allowance;
+mod backup;
+mod balance;
+mod calendar;
+mod command;
 mod config;
+mod export;
 mod history;
+mod ics;
+mod issues;
+mod lock;
+mod parse;
+mod search;
 mod state;
+mod summary;
+mod sync;
 
 use std::{
+    cell::{Cell, RefCell},
     cmp::Reverse,
-    collections::{BTreeMap, BTreeSet},
-    fs::File,
+    collections::BTreeMap,
+    fs,
     io::{self, Cursor},
     iter::successors,
     path::{Path, PathBuf},
+    time::{Duration as StdDuration, Instant},
 };
 
-pub use activity::{load_activities, store_activities, Activity, ActivityBeingBuilt, Selected};
-use history::{Action, History};
+pub use activity::{load_activities, store_activities, Activity, ActivityBeingBuilt};
+pub use allowance::Allowance;
+pub use backup::Backup;
+use balance::{load_ledger, store_ledger, Adjustment};
+pub use balance::{Balance, Ledger};
+use history::{load_history, store_history, Action, Entry, History};
 pub use state::ActivityVec;
 use state::State;
-use time::{macros::format_description, Date, Duration, OffsetDateTime};
+pub use summary::Summary;
+use time::{Date, Duration, PrimitiveDateTime, Time};
 
-use crate::util::{fmt_duration, is_weekend};
+use crate::util::{time_fmt::DATE_FMT, write_atomically, Zone};
 
-use self::activity::{load_days_off, load_holidays, parse_day, store_list_dates, ActivityId};
-use self::config::{load_config, store_config, Config};
+use self::activity::{load_calendar, load_days_off, store_days_off, store_list_dates, ActivityId};
+pub use self::calendar::DayKind;
+use self::calendar::{Calendar, DateList};
+pub use self::command::{ExCommand, COMMANDS};
+use self::config::{load_config, store_config, Config, SavePolicy};
+pub use self::export::ExportProfile;
+pub use self::ics::load_ics_dates;
+pub use self::issues::{IssueColumn, IssueReport};
+use self::lock::Lock;
+pub use self::parse::parse_day;
+use self::search::Search;
+use self::sync::{Contents, Fingerprint, Snapshot};
 use crate::app::config::ConfigBeingBuilt;
 use crate::traits::EditingPopUp;
 
+#[allow(clippy::enum_variant_names)]
 pub enum PopUp {
     EditingPopUp(Box<dyn EditingPopUp>),
     DaysOff {
         selected: usize,
         new_day_off: Option<String>,
+        replacing: Option<Date>,
     },
     Holidays {
         selected: usize,
         new_holiday: Option<String>,
+        replacing: Option<Date>,
     },
+    Issues {
+        selected: usize,
+        sort: IssueColumn,
+        descending: bool,
+    },
+    Search {
+        query: String,
+    },
+    History {
+        selected: usize,
+    },
+    /// The command line opened with `:`.
+    CommandLine {
+        line: String,
+    },
+    /// The keys of the pop up it was opened over, which comes back when it's closed.
+    Help {
+        under: Option<Box<PopUp>>,
+        title: &'static str,
+        lines: Vec<(String, &'static str)>,
+        selected: usize,
+    },
+    ExternalChange,
+    ConfirmQuit,
+}
+
+/// A change that can be repeated on the selected activity.
+#[derive(Debug, Clone)]
+enum Change {
+    Paste,
+    Delete,
+    Submit(ActivityBeingBuilt),
 }
 
+/// Something read from other files on first use, or why it couldn't be.
+type Cached<T> = RefCell<Option<io::Result<T>>>;
+
 pub enum PopUpType {
     Config,
     EditActivity,
@@ -47,58 +115,144 @@ pub struct App {
     conf_path: PathBuf,
     selected: Option<(Date, usize)>,
     activities: State,
-    days_off: BTreeSet<Reverse<Date>>,
-    holidays: BTreeSet<Reverse<Date>>,
+    calendar: Calendar,
+    ledger: Ledger,
     pop_up: Option<PopUp>,
     show_stats: bool,
     history: History,
     clipboard: Option<Activity>,
+    search: Option<Search>,
+    /// Whether the files were backed up this session.
+    backed_up: Cell<bool>,
+    /// Whether only the activities matching the search are shown.
+    filter: bool,
+    /// The files as they were last loaded or saved, to tell our changes apart from someone
+    /// else's.
+    base: RefCell<Snapshot>,
+    /// Whether a save was refused because the files were changed by something else.
+    changed_externally: Cell<bool>,
+    _lock: Option<Lock>,
+    /// Whether changes are disallowed and nothing is saved.
+    read_only: bool,
+    /// When the files were last loaded or saved.
+    last_saved: Cell<Instant>,
+    /// Whether something changed since the files were last loaded or saved.
+    dirty: Cell<bool>,
+    /// Whether unsaved changes are thrown away on quit.
+    discard: bool,
+    /// Whether a repeated action is running, it's saved once it's done.
+    repeating: bool,
+    last_change: Option<Change>,
+    /// The balance this file opens with, kept once computed since the files it's chained to have
+    /// to be read for it.
+    opening_balance: Cached<Duration>,
+    /// The days off of the files this one is chained to, kept like the opening balance.
+    previous_days_off: Cached<Vec<(Date, DayKind)>>,
+    /// Why the config file couldn't be loaded. The default config is used instead, and it isn't
+    /// saved over the file.
+    config_error: Option<String>,
     pub config: Config,
 }
 
 impl App {
-    pub fn load(p: String) -> io::Result<Self> {
-        let acts = load_activities(&p)?;
-        let days_off = load_days_off(&p)?;
-        let holidays = load_holidays(&p)?;
-        Ok(Self::new(p, acts, days_off, holidays))
+    /// Load the file, locking it unless it's opened `read_only`.
+    pub fn load(p: String, read_only: bool) -> io::Result<Self> {
+        let lock = if read_only {
+            None
+        } else {
+            Some(Lock::acquire(&p)?)
+        };
+        Self::open(p, read_only, lock)
+    }
+
+    /// Load the file to change it without locking it, for short changes while someone else has it
+    /// open. Saving still refuses to overwrite changes made since it was loaded, they have to be
+    /// merged with [`App::merge_external_changes`].
+    pub fn load_unlocked(p: String) -> io::Result<Self> {
+        Self::open(p, false, None)
+    }
+
+    fn open(p: String, read_only: bool, lock: Option<Lock>) -> io::Result<Self> {
+        let mut conf_path = dirs::config_dir().unwrap();
+        conf_path.push("effortrc");
+        let (config, config_error) = match load_config(&conf_path) {
+            Ok(config) => (config, None),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => (Config::default(), Some(e)),
+            Err(e) => return Err(e),
+        };
+        let fingerprint = Fingerprint::read(&p)?;
+        let acts = load_activities(&p, &config.home_zone)?;
+        let calendar = load_calendar(&p)?;
+        let ledger = load_ledger(&p)?;
+        let mut app = Self::new(p, conf_path, config, acts, calendar, ledger);
+        app.history = load_history(&app.filename)?;
+        app.base = RefCell::new(Snapshot {
+            fingerprint,
+            contents: app.contents(),
+        });
+        app._lock = lock;
+        app.read_only = read_only;
+        app.config_error = config_error.map(|e| e.to_string());
+        Ok(app)
     }
 
     pub fn new(
         filename: String,
+        conf_path: PathBuf,
+        config: Config,
         activities: Vec<Activity>,
-        days_off: Vec<Date>,
-        holidays: Vec<Date>,
+        calendar: Calendar,
+        ledger: Ledger,
     ) -> Self {
-        let mut conf_path = dirs::config_dir().unwrap();
-        conf_path.push("effortrc");
-        let config = load_config(conf_path.clone()).unwrap_or_default();
         Self {
             filename,
             conf_path,
             selected: None,
-            activities: activities
-                .into_iter()
-                .fold(
-                    BTreeMap::<Reverse<Date>, ActivityVec>::new(),
-                    |mut acc, a| {
-                        acc.entry(Reverse(a.day)).or_default().add(a);
-                        acc
-                    },
-                )
-                .into(),
-            days_off: days_off.into_iter().map(Reverse).collect(),
-            holidays: holidays.into_iter().map(Reverse).collect(),
+            activities: activities.into_iter().collect(),
+            calendar,
+            ledger,
             pop_up: None,
             show_stats: false,
             history: History::default(),
             clipboard: None,
+            search: None,
+            backed_up: Cell::new(false),
+            filter: false,
+            base: RefCell::default(),
+            changed_externally: Cell::new(false),
+            _lock: None,
+            read_only: false,
+            last_saved: Cell::new(Instant::now()),
+            dirty: Cell::new(false),
+            discard: false,
+            repeating: false,
+            last_change: None,
+            opening_balance: RefCell::new(None),
+            previous_days_off: RefCell::new(None),
+            config_error: None,
             config,
         }
     }
 
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Fails when the file was opened read-only.
+    fn writable(&self) -> Result<(), &'static str> {
+        if self.read_only {
+            Err("the file is open read-only")
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn is_holiday(&self, date: &time::Date) -> bool {
-        self.holidays.contains(&Reverse(*date))
+        self.calendar.get(*date) == Some(DayKind::Holiday)
     }
 
     pub fn is_free_holiday(&self, date: &time::Date) -> bool {
@@ -109,27 +263,115 @@ impl App {
         }
     }
 
-    pub fn n_workdays_so_far(&self) -> u16 {
-        let mut iter = self.activities.iter();
-        let last = match iter.next() {
-            Some((d, _)) => d.0,
-            None => return 0,
-        };
-        let mut first = match iter.next_back() {
-            Some((d, _)) => d.0,
-            None => return 1,
+    /// How much was worked so far compared to what was expected.
+    pub fn summary(&self) -> Summary {
+        Summary::new(
+            &self.activities,
+            &self.calendar,
+            &self.config,
+            self.config.home_zone.now(),
+        )
+    }
+
+    /// The overtime balance of `summary`, including what was carried over from the previous
+    /// files.
+    pub fn balance(&self, summary: &Summary) -> io::Result<Balance> {
+        let mut cached = self.opening_balance.borrow_mut();
+        let opening = cached.get_or_insert_with(|| {
+            self.ledger.opening_balance(
+                Path::new(&self.filename),
+                &self.config,
+                self.config.home_zone.now(),
+            )
+        });
+        match opening {
+            Ok(opening) => Ok(self.ledger.balance(*opening, summary)),
+            Err(e) => Err(io::Error::new(e.kind(), e.to_string())),
+        }
+    }
+
+    /// Forget what was read from the files this one is chained to, after something it depends on
+    /// changed.
+    fn forget_chain(&self) {
+        self.opening_balance.take();
+        self.previous_days_off.take();
+    }
+
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    /// The vacation allowance of the current year, if there's a policy configured. The days off
+    /// in the files this one is chained to are taken into account as well.
+    pub fn allowance(&self) -> io::Result<Option<Allowance>> {
+        let policy = match &self.config.vacation {
+            Some(policy) => policy,
+            None => return Ok(None),
         };
-        let mut counter = 0u16;
-        while first <= last {
-            if !is_weekend(&first) && !self.is_free_holiday(&first){
-                counter = counter.checked_add(1).expect("that's too many days bro");
+        let mut cached = self.previous_days_off.borrow_mut();
+        let previous = cached.get_or_insert_with(|| {
+            let mut days_off = Vec::new();
+            for file in self.ledger.previous_files(Path::new(&self.filename))? {
+                days_off.extend(load_days_off(file)?);
             }
-            first = first.next_day().unwrap();
+            Ok(days_off)
+        });
+        let mut days_off = match previous {
+            Ok(previous) => previous.iter().copied().collect::<BTreeMap<_, _>>(),
+            Err(e) => return Err(io::Error::new(e.kind(), e.to_string())),
+        };
+        days_off.extend(self.days_off().map(|(d, k)| (*d, k)));
+        let days_off = days_off.into_iter().collect::<Vec<_>>();
+        Ok(Some(
+            policy.allowance(&days_off, self.config.home_zone.now().date()),
+        ))
+    }
+
+    /// Record a manual change to the balance.
+    pub fn adjust_balance(
+        &mut self,
+        day: Date,
+        hours: f32,
+        note: String,
+    ) -> Result<(), &'static str> {
+        self.writable()?;
+        self.ledger
+            .adjustments
+            .push(Adjustment { day, hours, note });
+        self.dirty.set(true);
+        Ok(())
+    }
+
+    /// Start this file with the given balance instead of carrying it over from the previous one.
+    pub fn set_opening_balance(&mut self, hours: Option<f32>) -> Result<(), &'static str> {
+        self.writable()?;
+        self.ledger.opening = hours;
+        self.forget_chain();
+        self.dirty.set(true);
+        Ok(())
+    }
+
+    /// Carry over the closing balance of another activities file.
+    pub fn chain_balance(&mut self, previous: String) -> io::Result<()> {
+        if let Err(msg) = self.writable() {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, msg));
+        }
+        let prev = self.ledger.previous.replace(previous);
+        self.forget_chain();
+        if let Err(e) = self.balance(&self.summary()) {
+            self.ledger.previous = prev;
+            self.forget_chain();
+            return Err(e);
         }
-        counter
+        self.dirty.set(true);
+        Ok(())
     }
 
     pub fn next(&mut self) {
+        if self.filter {
+            let _ = self.next_match();
+            return;
+        }
         fn from_new_kv((date, _): (&Reverse<Date>, &ActivityVec)) -> (Date, usize) {
             (date.0, 0)
         }
@@ -157,6 +399,10 @@ impl App {
     }
 
     pub fn previous(&mut self) {
+        if self.filter {
+            let _ = self.previous_match();
+            return;
+        }
         fn from_new_kv((date, acts): (&Reverse<Date>, &ActivityVec)) -> (Date, usize) {
             (date.0, acts.len().saturating_sub(1))
         }
@@ -174,10 +420,20 @@ impl App {
     }
 
     pub fn select_first(&mut self) {
+        if self.filter {
+            self.selected = None;
+            let _ = self.next_match();
+            return;
+        }
         self.selected = self.activities.iter().next().map(|(d, _)| (d.0, 0));
     }
 
     pub fn select_last(&mut self) {
+        if self.filter {
+            self.selected = None;
+            let _ = self.previous_match();
+            return;
+        }
         self.selected = self
             .activities
             .iter()
@@ -185,6 +441,185 @@ impl App {
             .map(|(d, acts)| (d.0, acts.len() - 1))
     }
 
+    /// The position of the first activity after the selected one, in the order they are shown,
+    /// that satisfies `pred`. Wraps around the ends of the table.
+    fn find_from_selected<F>(&self, backwards: bool, pred: F) -> Option<(Date, usize)>
+    where
+        F: Fn(&Activity) -> bool,
+    {
+        let order = self
+            .activities
+            .iter()
+            .flat_map(|(date, acts)| {
+                acts.iter()
+                    .enumerate()
+                    .map(move |(index, a)| ((date.0, index), a))
+            })
+            .collect::<Vec<_>>();
+        let n = order.len();
+        let current = self
+            .selected
+            .and_then(|s| order.iter().position(|(pos, _)| *pos == s));
+        let candidates = (0..n).map(|k| match (current, backwards) {
+            (Some(c), false) => (c + 1 + k) % n,
+            (Some(c), true) => (c + n - 1 - k) % n,
+            (None, false) => k,
+            (None, true) => n - 1 - k,
+        });
+        candidates
+            .map(|i| order[i])
+            .find(|(_, a)| pred(a))
+            .map(|(pos, _)| pos)
+    }
+
+    pub fn start_search(&mut self) {
+        self.pop_up = Some(PopUp::Search {
+            query: String::new(),
+        })
+    }
+
+    /// Search for the query in the search prompt and select the first match after the selected
+    /// activity. An empty query repeats the last search.
+    pub fn submit_search(&mut self) -> Result<(), &'static str> {
+        if let Some(PopUp::Search { query }) = &self.pop_up {
+            if !query.is_empty() {
+                self.search = Some(Search::new(query.clone()));
+            }
+            self.pop_up = None;
+            self.next_match()?;
+        }
+        Ok(())
+    }
+
+    pub fn show_help(&mut self, title: &'static str, lines: Vec<(String, &'static str)>) {
+        let under = self.pop_up.take().map(Box::new);
+        self.pop_up = Some(PopUp::Help {
+            under,
+            title,
+            lines,
+            selected: 0,
+        })
+    }
+
+    pub fn hide_help(&mut self) {
+        if let Some(PopUp::Help { under, .. }) = self.pop_up.take() {
+            self.pop_up = under.map(|p| *p);
+        }
+    }
+
+    pub fn start_command_line(&mut self) {
+        self.pop_up = Some(PopUp::CommandLine {
+            line: String::new(),
+        })
+    }
+
+    /// Close the command line, returning what was typed in it.
+    pub fn take_command_line(&mut self) -> Option<String> {
+        match self.pop_up.take() {
+            Some(PopUp::CommandLine { line }) => Some(line),
+            pop_up => {
+                self.pop_up = pop_up;
+                None
+            }
+        }
+    }
+
+    /// Select the first activity of `day`.
+    pub fn goto_day(&mut self, day: Date) -> Result<(), &'static str> {
+        match self.activities.get(&Reverse(day)) {
+            Some(acts) if !acts.is_empty() => {
+                self.selected = Some((day, 0));
+                Ok(())
+            }
+            _ => Err("no activities on that day"),
+        }
+    }
+
+    /// Add `day` to the calendar as a day of the given kind, or remove it if it is one.
+    pub fn edit_calendar(
+        &mut self,
+        day: Date,
+        kind: DayKind,
+        add: bool,
+    ) -> Result<(), &'static str> {
+        if add {
+            if self.add_date(day, kind)? {
+                self.changed();
+            }
+            return Ok(());
+        }
+        self.writable()?;
+        match self.calendar.get(day) {
+            Some(DayKind::Holiday) if kind == DayKind::Holiday => {}
+            Some(k) if k != DayKind::Holiday && kind != DayKind::Holiday => {}
+            _ if kind == DayKind::Holiday => return Err("that day isn't a holiday"),
+            _ => return Err("that day isn't a day off"),
+        }
+        if self.remove_date(day) {
+            self.changed();
+        }
+        Ok(())
+    }
+
+    /// Change a setting of the config, validated like in the config pop up.
+    pub fn set_config(&mut self, key: &str, value: String) -> Result<(), &'static str> {
+        self.writable()?;
+        self.config_editable()?;
+        let mut builder = ConfigBeingBuilt::new(self.config.clone());
+        builder.set(key, value)?;
+        self.config = Config::try_from(&builder)?;
+        self.forget_chain();
+        self.changed();
+        Ok(())
+    }
+
+    pub fn search(&self) -> Option<&Search> {
+        self.search.as_ref()
+    }
+
+    pub fn next_match(&mut self) -> Result<(), &'static str> {
+        self.select_match(false)
+    }
+
+    pub fn previous_match(&mut self) -> Result<(), &'static str> {
+        self.select_match(true)
+    }
+
+    fn select_match(&mut self, backwards: bool) -> Result<(), &'static str> {
+        let search = self.search.as_ref().ok_or("no previous search")?;
+        let found = self
+            .find_from_selected(backwards, |a| search.matches(a))
+            .ok_or("no matches")?;
+        self.selected = Some(found);
+        Ok(())
+    }
+
+    /// Toggle hiding the activities that don't match the search.
+    pub fn toggle_filter(&mut self) -> Result<(), &'static str> {
+        if self.filter {
+            self.filter = false;
+            return Ok(());
+        }
+        let search = self.search.as_ref().ok_or("no search to filter by")?;
+        if !self.selected_activity().is_some_and(|a| search.matches(a)) {
+            self.next_match()?;
+        }
+        self.filter = true;
+        Ok(())
+    }
+
+    pub fn is_filtering(&self) -> bool {
+        self.filter
+    }
+
+    /// Whether the activity is shown in the table, it's hidden if it doesn't match the filter.
+    pub fn is_visible(&self, a: &Activity) -> bool {
+        match &self.search {
+            Some(search) if self.filter => search.matches(a),
+            _ => true,
+        }
+    }
+
     pub fn selected_id(&self) -> Option<ActivityId> {
         let (date, index) = self.selected?;
         self.activities
@@ -193,6 +628,14 @@ impl App {
             .map(|a| a.id)
     }
 
+    fn position_of(&self, id: ActivityId) -> Option<(Date, usize)> {
+        self.activities.iter().find_map(|(date, acts)| {
+            acts.iter()
+                .position(|a| a.id == id)
+                .map(|index| (date.0, index))
+        })
+    }
+
     fn selected_activity(&self) -> Option<&Activity> {
         self.selected.and_then(|(date, index)| {
             self.activities
@@ -202,39 +645,67 @@ impl App {
     }
 
     pub fn selected_issue_total_time(&self) -> Option<Duration> {
+        let now = self.config.home_zone.now();
         self.selected_activity().and_then(|act| {
             self.activities()
                 .flat_map(|(_, y)| y.iter())
                 .filter(|x| act.issue == x.issue)
-                .map(|x| x.end_time.map(|end_time| end_time - x.start_time))
-                .fold(None, |acc, x| match x {
-                    Some(z) => match acc {
-                        Some(a) => Some(a + z),
-                        None => Some(z),
-                    },
-                    _ => None,
-                })
+                .map(|x| x.time_spent(now))
+                .sum()
         })
     }
 
-    pub fn create_new_activity(&mut self) {
+    /// The activity whose timer is currently running, if any. It may have been started the day
+    /// before.
+    pub fn running_timer(&self) -> Option<&Activity> {
+        let now = self.config.home_zone.now();
+        successors(Some(now.date()), |d| d.previous_day())
+            .take(2)
+            .filter_map(|d| self.activities.get(&Reverse(d)))
+            .find_map(|acts| acts.iter().find(|a| a.is_running(now)))
+    }
+
+    pub fn create_new_activity(&mut self) -> Result<(), &'static str> {
+        self.writable()?;
         let last_time = self.selected_activity().and_then(|a| a.end_time);
         self.pop_up = Some(PopUp::EditingPopUp(Box::new(ActivityBeingBuilt::new(
             last_time,
+            &self.config.home_zone,
         ))));
+        Ok(())
+    }
+
+    pub fn config_error(&self) -> Option<&str> {
+        self.config_error.as_deref()
+    }
+
+    /// Fails when the config file couldn't be loaded, changes to the default config used instead
+    /// would replace it.
+    fn config_editable(&self) -> Result<(), &'static str> {
+        match self.config_error {
+            Some(_) => Err("the config file is invalid, fix it first"),
+            None => Ok(()),
+        }
     }
 
-    pub fn edit_config(&mut self) {
+    pub fn edit_config(&mut self) -> Result<(), &'static str> {
+        self.writable()?;
+        self.config_editable()?;
         self.pop_up = Some(PopUp::EditingPopUp(Box::new(ConfigBeingBuilt::new(
-            self.config,
+            self.config.clone(),
         ))));
+        Ok(())
     }
 
     pub fn editing(&self) -> bool {
         matches!(
-            self.pop_up
-                .as_ref()
-                .map(|a| matches!(a, PopUp::EditingPopUp(a) if a.is_editing())),
+            self.pop_up.as_ref().map(|a| match a {
+                PopUp::EditingPopUp(a) => a.is_editing(),
+                PopUp::Search { .. } | PopUp::CommandLine { .. } => true,
+                PopUp::DaysOff { new_day_off, .. } => new_day_off.is_some(),
+                PopUp::Holidays { new_holiday, .. } => new_holiday.is_some(),
+                _ => false,
+            }),
             Some(true)
         )
     }
@@ -261,6 +732,47 @@ impl App {
             .map(|(date, acts)| (&date.0, acts.as_slice()))
     }
 
+    /// The days that have activities or that activities started the day before ended on, most
+    /// recent first, with the activities started on each of them.
+    pub fn days(&self, now: PrimitiveDateTime) -> Vec<(Date, &[Activity])> {
+        static EMPTY: &[Activity] = &[];
+        let mut days = BTreeMap::new();
+        for (date, acts) in self.activities().filter(|(_, acts)| !acts.is_empty()) {
+            days.insert(Reverse(*date), acts);
+            if let Some(next) = date.next_day() {
+                let spills = acts.iter().any(|a| {
+                    a.time_spent_on(next, now)
+                        .is_some_and(|t| t > Duration::ZERO)
+                });
+                if spills {
+                    days.entry(Reverse(next)).or_insert(EMPTY);
+                }
+            }
+        }
+        days.into_iter().map(|(d, acts)| (d.0, acts)).collect()
+    }
+
+    /// The time worked on `day`, including the part of the activities from the day before that
+    /// ended on it, and whether some activity started that day has no end. Activities hidden by
+    /// the filter don't count.
+    pub fn time_spent_on(&self, day: Date, now: PrimitiveDateTime) -> (Duration, bool) {
+        let acts = |d: Option<Date>| {
+            d.and_then(|d| self.activities.get(&Reverse(d)))
+                .into_iter()
+                .flat_map(|acts| acts.iter())
+                .filter(|a| self.is_visible(a))
+        };
+        let mut some_none = false;
+        let started = acts(Some(day)).filter_map(|a| {
+            let time_spent = a.time_spent_on(day, now);
+            some_none |= time_spent.is_none();
+            time_spent
+        });
+        let carried = acts(day.previous_day()).filter_map(|a| a.time_spent_on(day, now));
+        let total = started.chain(carried).sum();
+        (total, some_none)
+    }
+
     #[allow(dead_code)]
     pub fn activities_filled(&self) -> impl Iterator<Item = (Date, &[Activity])> {
         static EMPTY: &[Activity] = &[];
@@ -274,67 +786,332 @@ impl App {
         })
     }
 
-    pub fn undo(&mut self) {
-        self.history.undo(&mut self.activities)
+    pub fn undo(&mut self) -> Result<(), &'static str> {
+        self.writable()?;
+        self.history.undo(&mut self.activities, &mut self.calendar);
+        self.clamp_pop_up_selection();
+        self.changed();
+        Ok(())
     }
 
-    pub fn redo(&mut self) {
-        self.history.redo(&mut self.activities)
+    pub fn redo(&mut self) -> Result<(), &'static str> {
+        self.writable()?;
+        self.history.redo(&mut self.activities, &mut self.calendar);
+        self.clamp_pop_up_selection();
+        self.changed();
+        Ok(())
     }
 
     pub fn save(&self) -> io::Result<()> {
         self.save_to(&self.filename)
     }
 
+    /// Write every file that changed. The first time something changes in a session the files
+    /// are backed up before being overwritten.
+    ///
+    /// If the files were changed by something else since they were loaded they are left alone,
+    /// the changes have to be reconciled first with [`App::reload`],
+    /// [`App::merge_external_changes`] or [`App::overwrite_external_changes`].
     pub fn save_to<P: AsRef<Path>>(&self, p: P) -> io::Result<()> {
-        let acts = self.activities.iter().flat_map(|(_, acts)| acts.iter());
-        File::create(p.as_ref()).and_then(|f| store_activities(f, acts))?;
-        File::create(self.conf_path.clone()).and_then(|f| store_config(f, self.config))?;
-        if !self.days_off.is_empty() {
-            File::create(format!("{}-off", p.as_ref().display()))
-                .and_then(|f| store_list_dates(f, self.days_off.iter().map(|d| &d.0)))?;
+        if self.read_only {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} is open read-only", self.filename),
+            ));
         }
-        if !self.holidays.is_empty() {
-            File::create(format!("{}-holidays", p.as_ref().display()))
-                .and_then(|f| store_list_dates(f, self.holidays.iter().map(|d| &d.0)))?;
+        let fingerprint = Fingerprint::read(&self.filename)?;
+        if fingerprint != self.base.borrow().fingerprint {
+            if self.contents() == self.base.borrow().contents {
+                // nothing of ours would be lost by not saving
+                self.dirty.set(false);
+                return Ok(());
+            }
+            self.changed_externally.set(true);
+            return Err(io::Error::other(format!(
+                "{} was changed by another program since it was loaded",
+                self.filename
+            )));
+        }
+        let files = self.files(p.as_ref())?;
+        let changed = files
+            .into_iter()
+            .filter(|(path, contents)| fs::read(path).ok().as_ref() != Some(contents))
+            .collect::<Vec<_>>();
+        if !changed.is_empty() {
+            self.backup_once()?;
+        }
+        for (path, contents) in changed {
+            write_atomically(path, &contents)?;
         }
+        self.save_config()?;
+        *self.base.borrow_mut() = Snapshot {
+            fingerprint: Fingerprint::read(&self.filename)?,
+            contents: self.contents(),
+        };
+        self.last_saved.set(Instant::now());
+        self.dirty.set(false);
         Ok(())
     }
 
-    pub fn export(&self) -> io::Result<()> {
-        let mut acts = self
-            .activities
-            .iter()
-            .flat_map(|(_, acts)| acts.iter())
-            .map(|a| {
-                if a.end_time.is_some() {
-                    Ok(a)
-                } else {
-                    Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        format!("activity {:?} doesn't have an end time", a),
-                    ))
-                }
-            })
-            .collect::<io::Result<Vec<_>>>()?;
-        acts.sort_unstable();
-        let mut w = csv::Writer::from_path(format!("{}-export.csv", self.filename))?;
-        static FMT: &[time::format_description::FormatItem<'static>] =
-            format_description!("[month]-[day]-[year]");
-        static TIME_FMT: &[time::format_description::FormatItem<'static>] =
-            format_description!("[hour repr:12]:[minute] [period]");
-        for a in acts.into_iter() {
-            w.write_record([
-                &a.day.format(FMT).expect("a correct format"),
-                &a.action,
-                &a.start_time.format(TIME_FMT).unwrap(),
-                &a.end_time.unwrap().format(TIME_FMT).unwrap(),
-                &fmt_duration(a.end_time.unwrap() - a.start_time),
-            ])?;
+    /// Write a copy of the files next to `p`, leaving the files of this session alone. Existing
+    /// files are only overwritten when `force` is set.
+    pub fn write_copy<P: AsRef<Path>>(&self, p: P, force: bool) -> io::Result<()> {
+        let p = p.as_ref();
+        // `./hours.csv` or a link to it is still the open file
+        let resolved = |p: &Path| fs::canonicalize(p).or_else(|_| std::path::absolute(p)).ok();
+        if resolved(p).is_some_and(|r| Some(r) == resolved(Path::new(&self.filename))) {
+            return self.save();
+        }
+        if p.exists() && !force {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists, use :w! to overwrite it", p.display()),
+            ));
+        }
+        for (path, contents) in self.files(p)? {
+            write_atomically(path, &contents)?;
+        }
+        Ok(())
+    }
+
+    /// The contents of the files stored next to `p`. Files that would be empty are left out
+    /// unless they already exist.
+    fn files(&self, p: &Path) -> io::Result<Vec<(PathBuf, Vec<u8>)>> {
+        let mut files = Vec::new();
+        let acts = self.activities.values().flat_map(|acts| acts.iter());
+        files.push((
+            p.to_path_buf(),
+            serialize(|w| store_activities(w, acts, &self.config.home_zone))?,
+        ));
+        let days_off_path = PathBuf::from(format!("{}-off", p.display()));
+        if !self.calendar.is_empty(DateList::DaysOff) || days_off_path.exists() {
+            let days_off = self.calendar.iter(DateList::DaysOff);
+            files.push((days_off_path, serialize(|w| store_days_off(w, days_off))?));
+        }
+        let holidays_path = PathBuf::from(format!("{}-holidays", p.display()));
+        if !self.calendar.is_empty(DateList::Holidays) || holidays_path.exists() {
+            files.push((
+                holidays_path,
+                serialize(|w| store_list_dates(w, self.holidays()))?,
+            ));
+        }
+        let history_path = PathBuf::from(format!("{}-history", p.display()));
+        if !self.history.is_empty() || history_path.exists() {
+            files.push((
+                history_path,
+                serialize(|w| store_history(w, &self.history))?,
+            ));
+        }
+        let ledger_path = PathBuf::from(format!("{}-balance", p.display()));
+        if !self.ledger.is_empty() || ledger_path.exists() {
+            files.push((ledger_path, serialize(|w| store_ledger(w, &self.ledger))?));
+        }
+        Ok(files)
+    }
+
+    /// Write the config, if it changed.
+    fn save_config(&self) -> io::Result<()> {
+        if self.config_error.is_some() {
+            return Ok(());
+        }
+        let config = serialize(|w| store_config(w, &self.config))?;
+        if fs::read(&self.conf_path).ok().as_ref() != Some(&config) {
+            write_atomically(&self.conf_path, &config)?;
+        }
+        Ok(())
+    }
+
+    /// Called after every change, saves it if that's the save policy. The change may have hidden
+    /// the selected activity from the filter, then the next one shown is selected.
+    fn changed(&mut self) {
+        self.dirty.set(true);
+        if self.filter && !self.selected_activity().is_some_and(|a| self.is_visible(a)) {
+            let _ = self.next_match();
+        }
+        if self.config.save_policy == SavePolicy::OnChange && !self.repeating {
+            let _ = self.save();
+        }
+    }
+
+    /// The changes made until [`App::end_repeat`] is called, by an action repeated with a count,
+    /// are undone at once and saved once.
+    pub fn start_repeat(&mut self) {
+        self.repeating = true;
+        self.history.start_group();
+    }
+
+    pub fn end_repeat(&mut self) {
+        self.repeating = false;
+        self.history.end_group();
+        if self.config.save_policy == SavePolicy::OnChange && self.is_dirty() {
+            let _ = self.save();
+        }
+    }
+
+    /// Save if the save policy is to do it periodically and it's time to.
+    pub fn save_if_due(&self) {
+        if let SavePolicy::Interval(secs) = self.config.save_policy {
+            if self.last_saved.get().elapsed() >= StdDuration::from_secs(secs) && self.is_dirty() {
+                let _ = self.save();
+            }
+        }
+    }
+
+    /// Whether there are changes that weren't saved.
+    pub fn is_dirty(&self) -> bool {
+        !self.read_only && self.dirty.get()
+    }
+
+    pub fn confirm_quit(&mut self) {
+        self.pop_up = Some(PopUp::ConfirmQuit);
+    }
+
+    pub fn hide_confirm_quit(&mut self) {
+        self.pop_up = None;
+    }
+
+    /// Don't save the unsaved changes when the app is dropped.
+    pub fn discard_changes(&mut self) {
+        self.discard = true;
+    }
+
+    fn contents(&self) -> Contents {
+        Contents {
+            activities: self
+                .activities
+                .values()
+                .flat_map(|acts| acts.iter())
+                .map(|a| (a.id, a.clone()))
+                .collect(),
+            calendar: self.calendar.all().map(|(d, k)| (*d, k)).collect(),
+            ledger: self.ledger.clone(),
         }
+    }
+
+    fn set_contents(&mut self, contents: Contents) {
+        let selected = self.selected_id();
+        self.activities = contents.activities.into_values().collect();
+        self.calendar = contents.calendar.into_iter().collect();
+        self.ledger = contents.ledger;
+        self.forget_chain();
+        self.dirty.set(true);
+        self.selected = selected.and_then(|id| self.position_of(id));
+        self.clamp_pop_up_selection();
+    }
+
+    /// Whether a save was refused because the files were changed by something else.
+    pub fn changed_externally(&self) -> bool {
+        self.changed_externally.get()
+    }
+
+    pub fn show_external_change(&mut self) {
+        self.pop_up = Some(PopUp::ExternalChange);
+    }
+
+    /// Keep the files as they are, the next save will notice the change again.
+    pub fn hide_external_change(&mut self) {
+        self.changed_externally.set(false);
+        self.pop_up = None;
+    }
+
+    /// Throw away the changes made in this session and load the files again.
+    pub fn reload(&mut self) -> io::Result<()> {
+        let fingerprint = Fingerprint::read(&self.filename)?;
+        let contents = load_contents(&self.filename, &self.config.home_zone)?;
+        self.history = load_history(&self.filename)?;
+        self.set_contents(contents.clone());
+        *self.base.borrow_mut() = Snapshot {
+            fingerprint,
+            contents,
+        };
+        self.dirty.set(false);
+        self.hide_external_change();
         Ok(())
     }
 
+    /// Combine the changes made in this session with the ones made to the files by something
+    /// else. When both changed the same activity or day, the change from this session is kept.
+    pub fn merge_external_changes(&mut self) -> io::Result<()> {
+        let fingerprint = Fingerprint::read(&self.filename)?;
+        let theirs = load_contents(&self.filename, &self.config.home_zone)?;
+        let merged = self.base.borrow().contents.merge(&self.contents(), &theirs);
+        self.set_contents(merged);
+        *self.base.borrow_mut() = Snapshot {
+            fingerprint,
+            contents: theirs,
+        };
+        self.hide_external_change();
+        self.save()
+    }
+
+    /// Replace the files with what's in this session, dropping the changes made by something
+    /// else.
+    pub fn overwrite_external_changes(&mut self) -> io::Result<()> {
+        self.base.borrow_mut().fingerprint = Fingerprint::read(&self.filename)?;
+        self.hide_external_change();
+        self.save()
+    }
+
+    /// Keep a copy of the files as they were before this session changed them.
+    fn backup_once(&self) -> io::Result<()> {
+        if self.backed_up.replace(true) {
+            return Ok(());
+        }
+        backup::backup(
+            &self.filename,
+            &self.config.backup_dir(),
+            self.config.backups,
+        )
+    }
+
+    /// The backups of this file, most recent first.
+    pub fn backups(&self) -> io::Result<Vec<Backup>> {
+        backup::list_backups(&self.filename, &self.config.backup_dir())
+    }
+
+    /// Replace everything in this file with the contents of the backup with the given name. The
+    /// current contents are backed up first.
+    pub fn restore_backup(&mut self, name: &str) -> io::Result<()> {
+        if let Err(msg) = self.writable() {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, msg));
+        }
+        let backup = self
+            .backups()?
+            .into_iter()
+            .find(|b| b.name == name)
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("no backup named {}", name))
+            })?;
+        let data_file = backup.data_file(&self.filename);
+        let activities = load_activities(&data_file, &self.config.home_zone)?;
+        let calendar = load_calendar(&data_file)?;
+        let ledger = load_ledger(&data_file)?;
+        let history = load_history(&data_file)?;
+        self.backup_once()?;
+        self.activities = activities.into_iter().collect();
+        self.calendar = calendar;
+        self.ledger = ledger;
+        self.forget_chain();
+        self.history = history;
+        self.dirty.set(true);
+        self.selected = None;
+        self.save()
+    }
+
+    pub fn export(
+        &self,
+        profile: &ExportProfile,
+        from: Option<Date>,
+        to: Option<Date>,
+    ) -> io::Result<()> {
+        profile.write(
+            &self.filename,
+            self.activities.values().flat_map(|acts| acts.iter()),
+            from,
+            to,
+        )
+    }
+
     pub fn cancel_edit(&mut self) {
         self.pop_up = None
     }
@@ -353,6 +1130,7 @@ impl App {
         self.pop_up = Some(PopUp::DaysOff {
             selected: 0,
             new_day_off: None,
+            replacing: None,
         })
     }
 
@@ -360,6 +1138,7 @@ impl App {
         self.pop_up = Some(PopUp::Holidays {
             selected: 0,
             new_holiday: None,
+            replacing: None,
         })
     }
 
@@ -375,49 +1154,116 @@ impl App {
         }
     }
 
+    pub fn show_issues(&mut self) {
+        self.pop_up = Some(PopUp::Issues {
+            selected: 0,
+            sort: IssueColumn::Total,
+            descending: true,
+        })
+    }
+
+    pub fn hide_issues(&mut self) {
+        if matches!(self.pop_up, Some(PopUp::Issues { .. })) {
+            self.pop_up = None
+        }
+    }
+
+    /// Every issue worked on, sorted like the issues pop up says.
+    pub fn issues(&self) -> Vec<IssueReport> {
+        let (sort, descending) = match self.pop_up {
+            Some(PopUp::Issues {
+                sort, descending, ..
+            }) => (sort, descending),
+            _ => (IssueColumn::Total, true),
+        };
+        issues::issue_breakdown(
+            self.activities.values().flat_map(|acts| acts.iter()),
+            self.config.home_zone.now(),
+            sort,
+            descending,
+        )
+    }
+
+    /// Close the issues pop up and select the most recent activity of the selected issue.
+    pub fn jump_to_selected_issue(&mut self) {
+        let issue = match self.pop_up {
+            Some(PopUp::Issues { selected, .. }) => self.issues().into_iter().nth(selected),
+            _ => None,
+        };
+        if let Some(issue) = issue {
+            self.selected = self.activities.iter().find_map(|(date, acts)| {
+                acts.iter()
+                    .rposition(|a| a.issue == issue.issue)
+                    .map(|index| (date.0, index))
+            });
+            self.pop_up = None;
+        }
+    }
+
+    pub fn show_history(&mut self) {
+        self.pop_up = Some(PopUp::History { selected: 0 })
+    }
+
+    pub fn hide_history(&mut self) {
+        if matches!(self.pop_up, Some(PopUp::History { .. })) {
+            self.pop_up = None
+        }
+    }
+
+    /// The actions that can be undone, most recent first.
+    pub fn history(&self) -> impl Iterator<Item = &Entry> {
+        self.history.past()
+    }
+
+    pub fn n_history(&self) -> usize {
+        self.history.past().count()
+    }
+
     pub fn n_days_off(&self) -> usize {
-        self.days_off.len()
+        self.calendar.len(DateList::DaysOff)
     }
 
     pub fn n_holidays(&self) -> usize {
-        self.holidays.len()
+        self.calendar.len(DateList::Holidays)
     }
 
-    pub fn n_days_off_up_to_today(&self) -> u16 {
-        let today = OffsetDateTime::now_local()
-            .unwrap_or_else(|_| OffsetDateTime::now_utc())
-            .date();
-        self.days_off
-            .iter()
-            .filter(|d| d.0 <= today)
-            .count()
-            .try_into()
-            .expect("that's too many days off bro")
+    /// How many days of each kind were taken off up to today.
+    pub fn days_off_up_to_today(&self) -> BTreeMap<DayKind, f32> {
+        let today = self.config.home_zone.now().date();
+        self.calendar
+            .iter(DateList::DaysOff)
+            .filter(|(d, _)| **d <= today)
+            .fold(BTreeMap::new(), |mut acc, (_, kind)| {
+                *acc.entry(kind).or_default() += kind.fraction();
+                acc
+            })
     }
 
     pub fn submit(&mut self) -> Result<(), &'static str> {
         if let Some(PopUp::EditingPopUp(new)) = &self.pop_up() {
             match new.popup_type() {
                 crate::app::PopUpType::Config => {
-                    let config: Config = (&**new)
+                    let config: Config = (**new)
                         .as_any()
                         .downcast_ref::<ConfigBeingBuilt>()
                         .unwrap()
                         .try_into()?;
                     self.config = config;
+                    self.forget_chain();
                 }
                 crate::app::PopUpType::EditActivity => {
-                    let activity: Activity = (&**new)
+                    let builder = (**new)
                         .as_any()
                         .downcast_ref::<ActivityBeingBuilt>()
-                        .unwrap()
-                        .try_into()?;
+                        .unwrap();
+                    let activity: Activity = builder.try_into()?;
+                    self.last_change = Some(Change::Submit(builder.clone()));
                     self.add_activity(activity);
                 }
             }
         }
         self.pop_up = None;
-        let _ = self.save_to(&self.filename);
+        self.changed();
         Ok(())
     }
 
@@ -426,15 +1272,31 @@ impl App {
             Some(PopUp::DaysOff {
                 new_day_off: Some(d),
                 selected,
+                replacing,
             }) => {
-                let date = parse_day(d)?;
+                let (date, kind) = match d.trim().rsplit_once(' ') {
+                    Some((date, kind)) if kind.parse::<DayKind>().is_ok() => {
+                        (date, kind.parse().ok())
+                    }
+                    _ => (d.as_str(), None),
+                };
+                let date = parse_day(date)?;
                 let selected = *selected;
-                self.add_day_off(date)?;
+                let changed = match *replacing {
+                    Some(prev) => {
+                        let prev_kind = self.calendar.get(prev).unwrap_or(DayKind::Vacation);
+                        self.replace_date(prev, date, kind.unwrap_or(prev_kind))?
+                    }
+                    None => self.add_date(date, kind.unwrap_or(DayKind::Vacation))?,
+                };
                 self.pop_up = Some(PopUp::DaysOff {
                     selected,
                     new_day_off: None,
+                    replacing: None,
                 });
-                let _ = self.save_to(&self.filename);
+                if changed {
+                    self.changed();
+                }
                 Ok(())
             }
             _ => Ok(()),
@@ -446,57 +1308,139 @@ impl App {
             Some(PopUp::Holidays {
                 new_holiday: Some(d),
                 selected,
+                replacing,
             }) => {
                 let date = parse_day(d)?;
                 let selected = *selected;
-                self.add_holiday(date)?;
+                let changed = match *replacing {
+                    Some(prev) => self.replace_date(prev, date, DayKind::Holiday)?,
+                    None => self.add_date(date, DayKind::Holiday)?,
+                };
                 self.pop_up = Some(PopUp::Holidays {
                     selected,
                     new_holiday: None,
+                    replacing: None,
                 });
-                let _ = self.save_to(&self.filename);
+                if changed {
+                    self.changed();
+                }
                 Ok(())
             }
             _ => Ok(()),
         }
     }
 
-    pub fn add_day_off(&mut self, date: Date) -> Result<(), &'static str> {
-        if self.activities.contains_key(&Reverse(date)) {
-            Err("you worked that day, can't take it off")
-        } else if is_weekend(&date) {
-            Err("can't take weekends off")
-        } else {
-            self.days_off.insert(Reverse(date));
-            Ok(())
+    /// Stop typing a date in the days off or holidays pop up.
+    pub fn cancel_date_input(&mut self) {
+        match &mut self.pop_up {
+            Some(PopUp::DaysOff {
+                new_day_off: input,
+                replacing,
+                ..
+            })
+            | Some(PopUp::Holidays {
+                new_holiday: input,
+                replacing,
+                ..
+            }) => {
+                *input = None;
+                *replacing = None;
+            }
+            _ => {}
         }
     }
 
-    pub fn add_holiday(&mut self, date: Date) -> Result<(), &'static str> {
-        if self.activities.contains_key(&Reverse(date)) {
-            Err("you worked on a holiday?!")
-        } else {
-            self.holidays.insert(Reverse(date));
-            Ok(())
+    /// Start editing the selected day off in place.
+    pub fn edit_selected_day_off(&mut self) -> Result<(), &'static str> {
+        self.writable()?;
+        if let Some(PopUp::DaysOff {
+            selected,
+            new_day_off,
+            replacing,
+        }) = &mut self.pop_up
+        {
+            if let Some((date, kind)) = self.calendar.iter(DateList::DaysOff).nth(*selected) {
+                *new_day_off = Some(format!(
+                    "{} {}",
+                    date.format(DATE_FMT).unwrap(),
+                    kind.as_str()
+                ));
+                *replacing = Some(*date);
+            }
+        }
+        Ok(())
+    }
+
+    /// Start editing the selected holiday in place.
+    pub fn edit_selected_holiday(&mut self) -> Result<(), &'static str> {
+        self.writable()?;
+        if let Some(PopUp::Holidays {
+            selected,
+            new_holiday,
+            replacing,
+        }) = &mut self.pop_up
+        {
+            if let Some((date, _)) = self.calendar.iter(DateList::Holidays).nth(*selected) {
+                *new_holiday = Some(date.format(DATE_FMT).unwrap());
+                *replacing = Some(*date);
+            }
         }
+        Ok(())
     }
 
-    pub fn days_off(&self) -> impl Iterator<Item = &Date> {
-        self.days_off.iter().map(|d| &d.0)
+    pub fn days_off(&self) -> impl Iterator<Item = (&Date, DayKind)> {
+        self.calendar.iter(DateList::DaysOff)
     }
 
     pub fn holidays(&self) -> impl Iterator<Item = &Date> {
-        self.holidays.iter().map(|d| &d.0)
+        self.calendar.iter(DateList::Holidays).map(|(d, _)| d)
+    }
+
+    /// Keep the selection of the days off and holidays pop ups inside their lists.
+    fn clamp_pop_up_selection(&mut self) {
+        let (selected, len) = match &mut self.pop_up {
+            Some(PopUp::DaysOff { selected, .. }) => {
+                (selected, self.calendar.len(DateList::DaysOff))
+            }
+            Some(PopUp::Holidays { selected, .. }) => {
+                (selected, self.calendar.len(DateList::Holidays))
+            }
+            Some(PopUp::History { selected }) => (selected, self.history.past().count()),
+            _ => return,
+        };
+        *selected = (*selected).min(len.saturating_sub(1));
+    }
+
+    fn check_date(&self, date: Date, kind: DayKind) -> Result<(), &'static str> {
+        let worked = self.activities.contains_key(&Reverse(date));
+        match (kind, self.calendar.get(date)) {
+            (DayKind::Holiday, Some(k)) if k != DayKind::Holiday => {
+                Err("that day was already taken off")
+            }
+            (_, Some(DayKind::Holiday)) if kind != DayKind::Holiday => {
+                Err("that day is already a holiday")
+            }
+            (DayKind::Holiday, _) if worked => Err("you worked on a holiday?!"),
+            // you're supposed to work on the other half of a half day
+            (_, _) if worked && kind != DayKind::HalfDay => {
+                Err("you worked that day, can't take it off")
+            }
+            (_, _) if self.config.expected_hours(date) <= 0.0 => {
+                Err("you aren't expected to work that day")
+            }
+            _ => Ok(()),
+        }
     }
 }
 
 /// Actions that influence the history
 impl App {
     /// Start editig the currently selected activity
-    pub fn edit_activity(&mut self) {
+    pub fn edit_activity(&mut self) -> Result<(), &'static str> {
+        self.writable()?;
         let (date, index) = match self.selected {
             Some(s) => s,
-            None => return,
+            None => return Ok(()),
         };
         let (act, last) = match self
             .activities
@@ -504,45 +1448,292 @@ impl App {
             .and_then(|a| a.get(index).map(|x| (x, a.get(index.saturating_sub(1)))))
         {
             Some(acts) => acts,
-            None => return,
+            None => return Ok(()),
         };
-        let act: ActivityBeingBuilt = (act, last.and_then(|a| a.end_time)).into();
+        let act: ActivityBeingBuilt =
+            (act, last.and_then(|a| a.end_time), &self.config.home_zone).into();
         self.pop_up = Some(PopUp::EditingPopUp(Box::new(act)));
-        let _ = self.save_to(&self.filename);
+        Ok(())
     }
 
     /// Delete the currently selected activity
-    pub fn delete_activity(&mut self) {
+    pub fn delete_activity(&mut self) -> Result<(), &'static str> {
+        self.writable()?;
         let (date, index) = match self.selected {
             Some(s) => s,
-            None => return,
+            None => return Ok(()),
         };
         if let Some(act) = self.activities.remove(date, index) {
             self.clipboard = Some(act.clone());
-            self.history.frwd(Action::DeleteActivity(act))
+            self.history.frwd(Action::DeleteActivity(act));
+            self.last_change = Some(Change::Delete);
+            self.changed();
         }
-        let _ = self.save_to(&self.filename);
+        Ok(())
     }
 
     pub fn paste(&mut self) -> Result<(), &'static str> {
+        self.writable()?;
         let mut to_paste = match &self.clipboard {
             Some(s) => s.clone(),
             None => return Err("clipboard is empty"),
         };
         to_paste.id = ActivityId::default();
-        let length = to_paste.end_time.map(|e| e - to_paste.start_time);
+        let length = to_paste.end().map(|e| e - to_paste.start());
         if let Some(selected) = self.selected_activity() {
-            if let Some(end_time) = selected.end_time {
-                to_paste.start_time = end_time;
+            // the end of an activity that crosses midnight is on the next day
+            match selected.end() {
+                Some(end) => {
+                    to_paste.day = end.date();
+                    to_paste.start_time = end.time();
+                }
+                None => to_paste.day = selected.day,
             }
-            to_paste.day = selected.day;
         }
         to_paste.end_time = length.map(|l| to_paste.start_time + l);
+        // select the copy so the next paste goes after it
+        let id = to_paste.id;
         self.add_activity(to_paste);
-        let _ = self.save_to(&self.filename);
+        self.selected = self.position_of(id);
+        self.last_change = Some(Change::Paste);
+        self.changed();
+        Ok(())
+    }
+
+    /// Repeat the last paste, delete or submitted edit on the selected activity. Repeated edits
+    /// move to the selected activity's day, added activities are added to it again.
+    pub fn repeat_last_change(&mut self) -> Result<(), &'static str> {
+        match self.last_change.clone() {
+            Some(Change::Paste) => self.paste(),
+            Some(Change::Delete) => self.delete_activity(),
+            Some(Change::Submit(builder)) => {
+                self.writable()?;
+                let builder = builder.repeated_on(self.selected_activity());
+                let activity: Activity = (&builder).try_into()?;
+                let id = activity.id;
+                self.add_activity(activity);
+                self.selected = self.position_of(id);
+                self.changed();
+                Ok(())
+            }
+            None => Err("nothing to repeat"),
+        }
+    }
+
+    /// Start a timer on the selected activity's issue, stopping the one currently running.
+    pub fn start_timer(&mut self) -> Result<(), &'static str> {
+        let (action, issue) = match self.selected_activity() {
+            Some(a) => (a.action.clone(), a.issue.clone()),
+            None => return Err("nothing selected"),
+        };
+        self.start_timer_on(action, issue)
+    }
+
+    /// Start a timer on the given issue, stopping the one currently running.
+    pub fn start_timer_on(&mut self, action: String, issue: String) -> Result<(), &'static str> {
+        self.writable()?;
+        if action.is_empty() {
+            return Err("action field is mandatory");
+        }
+        if self.running_timer().is_some() {
+            self.stop_timer()?;
+        }
+        let mut act = Activity::start_at(self.config.home_zone.now(), action, issue);
+        act.offset = self.config.home_zone.away_offset();
+        let id = act.id;
+        self.add_activity(act);
+        self.selected = self.position_of(id);
+        self.changed();
+        Ok(())
+    }
+
+    /// Stop the running timer at the current time.
+    pub fn stop_timer(&mut self) -> Result<(), &'static str> {
+        self.writable()?;
+        let mut act = match self.running_timer() {
+            Some(a) => a.clone(),
+            None => return Err("no timer running"),
+        };
+        let now = self.config.home_zone.now();
+        act.end_time = Some(Time::from_hms(now.hour(), now.minute(), 0).unwrap());
+        self.add_activity(act);
+        self.changed();
+        Ok(())
+    }
+
+    /// Add the activity described by `builder`, without going through the popup.
+    pub fn submit_activity(&mut self, builder: &ActivityBeingBuilt) -> Result<(), &'static str> {
+        self.writable()?;
+        let activity = builder.try_into()?;
+        self.add_activity(activity);
+        self.changed();
+        Ok(())
+    }
+
+    pub fn add_day_off(&mut self, date: Date, kind: DayKind) -> Result<(), &'static str> {
+        if self.add_date(date, kind)? {
+            self.changed();
+        }
+        Ok(())
+    }
+
+    pub fn add_holiday(&mut self, date: Date) -> Result<(), &'static str> {
+        if self.add_date(date, DayKind::Holiday)? {
+            self.changed();
+        }
+        Ok(())
+    }
+
+    /// Delete the day off selected in the days off pop up
+    pub fn delete_selected_day_off(&mut self) -> Result<(), &'static str> {
+        self.writable()?;
+        if let Some(PopUp::DaysOff { selected, .. }) = self.pop_up {
+            let date = self.days_off().nth(selected).map(|(d, _)| *d);
+            if date.is_some_and(|date| self.remove_date(date)) {
+                self.clamp_pop_up_selection();
+                self.changed();
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete the holiday selected in the holidays pop up
+    pub fn delete_selected_holiday(&mut self) -> Result<(), &'static str> {
+        self.writable()?;
+        if let Some(PopUp::Holidays { selected, .. }) = self.pop_up {
+            let date = self.holidays().nth(selected).copied();
+            if date.is_some_and(|date| self.remove_date(date)) {
+                self.clamp_pop_up_selection();
+                self.changed();
+            }
+        }
+        Ok(())
+    }
+
+    /// Change the selected day off to the next kind of day off
+    pub fn cycle_selected_day_off_kind(&mut self) -> Result<(), &'static str> {
+        self.writable()?;
+        if let Some(PopUp::DaysOff { selected, .. }) = self.pop_up {
+            let day_off = self.days_off().nth(selected).map(|(d, k)| (*d, k));
+            if let Some((date, kind)) = day_off {
+                if self.replace_date(date, date, kind.next_day_off())? {
+                    self.changed();
+                }
+            }
+        }
         Ok(())
     }
 
+    /// Undo the action selected in the history pop up by doing its opposite, without undoing
+    /// the actions that came after it.
+    pub fn revert_selected_history_entry(&mut self) -> Result<(), &'static str> {
+        self.writable()?;
+        let action = match self.pop_up {
+            Some(PopUp::History { selected }) => match self.history.past().nth(selected) {
+                Some(entry) => entry.action.clone(),
+                None => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+        let exists = |app: &Self, a: &Activity| app.position_of(a.id).is_some();
+        let changed = match action {
+            Action::DeleteActivity(a) if !exists(self, &a) => {
+                self.add_activity(a);
+                true
+            }
+            Action::DeleteActivity(_) => return Err("that activity was added back already"),
+            Action::Edit { prev } if exists(self, &prev) => {
+                let unchanged = self
+                    .activities
+                    .values()
+                    .flat_map(|acts| acts.iter())
+                    .any(|a| *a == prev);
+                if !unchanged {
+                    self.add_activity(prev);
+                }
+                !unchanged
+            }
+            Action::AddActivity(a) if exists(self, &a) => {
+                match self.activities.remove_by_id(a.day, a.id) {
+                    Some(a) => {
+                        self.history.frwd(Action::DeleteActivity(a));
+                        true
+                    }
+                    None => false,
+                }
+            }
+            Action::Edit { .. } | Action::AddActivity(_) => {
+                return Err("that activity no longer exists")
+            }
+            Action::AddDate(date, kind) if self.calendar.get(date) == Some(kind) => {
+                self.remove_date(date)
+            }
+            Action::ReplaceDate { prev, new } if self.calendar.get(new.0) == Some(new.1) => {
+                self.replace_date(new.0, prev.0, prev.1)?
+            }
+            Action::AddDate(..) | Action::ReplaceDate { .. } => {
+                return Err("that date was changed since")
+            }
+            Action::RemoveDate(date, kind) => self.add_date(date, kind)?,
+        };
+        if changed {
+            self.pop_up = Some(PopUp::History { selected: 0 });
+            self.clamp_pop_up_selection();
+            self.changed();
+        }
+        Ok(())
+    }
+
+    /// Put `date` in the calendar as a day of `kind`, returns whether the calendar changed.
+    ///
+    /// Like [`App::remove_date`] and [`App::replace_date`] this only records the change in the
+    /// history, the caller has to call [`App::changed`] once it is done.
+    fn add_date(&mut self, date: Date, kind: DayKind) -> Result<bool, &'static str> {
+        self.writable()?;
+        self.check_date(date, kind)?;
+        match self.calendar.insert(date, kind) {
+            None => self.history.frwd(Action::AddDate(date, kind)),
+            Some(prev) if prev != kind => self.history.frwd(Action::ReplaceDate {
+                prev: (date, prev),
+                new: (date, kind),
+            }),
+            Some(_) => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn remove_date(&mut self, date: Date) -> bool {
+        match self.calendar.remove(date) {
+            Some(kind) => {
+                self.history.frwd(Action::RemoveDate(date, kind));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn replace_date(&mut self, from: Date, to: Date, kind: DayKind) -> Result<bool, &'static str> {
+        let prev = match self.calendar.get(from) {
+            Some(prev_kind) if from == to && prev_kind == kind => return Ok(false),
+            Some(prev_kind) => (from, prev_kind),
+            None => return self.add_date(to, kind),
+        };
+        if from != to && self.calendar.get(to).is_some() {
+            return Err("that date is already in the calendar");
+        }
+        self.calendar.remove(from);
+        if let Err(e) = self.check_date(to, kind) {
+            self.calendar.insert(prev.0, prev.1);
+            return Err(e);
+        }
+        self.calendar.insert(to, kind);
+        self.history.frwd(Action::ReplaceDate {
+            prev,
+            new: (to, kind),
+        });
+        Ok(true)
+    }
+
     fn add_activity(&mut self, a: Activity) {
         match self.activities.add(a.clone()) {
             Some(prev) => self.history.frwd(Action::Edit { prev }),
@@ -551,15 +1742,45 @@ impl App {
     }
 }
 
+fn load_contents(filename: &str, home: &Zone) -> io::Result<Contents> {
+    let activities = load_activities(filename, home)?;
+    let calendar = load_calendar(filename)?;
+    Ok(Contents {
+        activities: activities.into_iter().map(|a| (a.id, a)).collect(),
+        calendar: calendar.all().map(|(d, k)| (*d, k)).collect(),
+        ledger: load_ledger(filename)?,
+    })
+}
+
+fn serialize<F>(store: F) -> io::Result<Vec<u8>>
+where
+    F: FnOnce(&mut Vec<u8>) -> io::Result<()>,
+{
+    let mut contents = Vec::new();
+    store(&mut contents)?;
+    Ok(contents)
+}
+
 impl Drop for App {
     fn drop(&mut self) {
-        println!("Auto saving file");
+        if self.discard || !self.is_dirty() {
+            return;
+        }
+        if self.config.save_policy == SavePolicy::Manual {
+            eprintln!(
+                "The changes to '{}' weren't saved, the save policy is manual",
+                self.filename
+            );
+            return;
+        }
+        eprintln!("Auto saving file");
         if let Err(e) = self.save() {
             eprintln!("Fatal error writing file '{}'!!", self.filename);
             eprintln!("{:?}", e);
             let mut s = Vec::new();
             let c = Cursor::new(&mut s);
-            match store_activities(c, self.activities.iter().flat_map(|(_, acts)| acts.iter())) {
+            let acts = self.activities.values().flat_map(|acts| acts.iter());
+            match store_activities(c, acts, &self.config.home_zone) {
                 Ok(_) => eprintln!("{}", String::from_utf8_lossy(&s)),
                 Err(e) => {
                     eprintln!("Failed to serialize csv in memory: {:?}", e);
@@ -569,3 +1790,66 @@ impl Drop for App {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use time::macros::date;
+
+    use super::*;
+
+    fn app(activities: Vec<Activity>) -> App {
+        let config = Config {
+            save_policy: SavePolicy::Manual,
+            ..Config::default()
+        };
+        App::new(
+            "test.csv".into(),
+            PathBuf::from("config.json"),
+            config,
+            activities,
+            Calendar::default(),
+            Ledger::default(),
+        )
+    }
+
+    #[test]
+    fn revert_date_entries() {
+        let day = date!(2022 - 01 - 04);
+        let mut app = app(Vec::new());
+        app.add_day_off(day, DayKind::Vacation).unwrap();
+        app.pop_up = Some(PopUp::History { selected: 0 });
+        app.revert_selected_history_entry().unwrap();
+        assert_eq!(app.calendar.get(day), None);
+        assert_eq!(app.history.past().count(), 2);
+        app.revert_selected_history_entry().unwrap();
+        assert_eq!(app.calendar.get(day), Some(DayKind::Vacation));
+        assert_eq!(app.history.past().count(), 3);
+    }
+
+    #[test]
+    fn repeated_delete() {
+        let day = date!(2022 - 01 - 03);
+        let acts = ["coding", "review"]
+            .into_iter()
+            .map(|action| Activity::start_at(day.midnight(), action.into(), "".into()))
+            .collect();
+        let mut app = app(acts);
+        app.selected = Some((day, 0));
+        app.start_repeat();
+        for _ in 0..3 {
+            app.delete_activity().unwrap();
+        }
+        app.end_repeat();
+        assert!(app.activities.is_empty());
+        assert_eq!(app.history.past().count(), 2);
+    }
+
+    #[test]
+    fn delete_nothing() {
+        let mut app = app(Vec::new());
+        app.selected = Some((date!(2022 - 01 - 03), 0));
+        app.delete_activity().unwrap();
+        assert!(!app.is_dirty());
+        assert!(app.history.past().next().is_none());
+    }
+}
@@ -1,30 +1,43 @@
 use std::{
     any::Any,
+    collections::HashSet,
+    fmt,
     fs::File,
     io::{self, BufReader, BufWriter, Read, Write},
     marker::PhantomData,
     path::Path,
-    sync::atomic::{AtomicUsize, Ordering},
 };
 
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use time::{
-    format_description::FormatItem, macros::format_description, Date, Month, OffsetDateTime, Time,
+    format_description::FormatItem, macros::format_description, Date, Duration, PrimitiveDateTime,
+    Time, UtcOffset,
 };
+use uuid::Uuid;
 
 use tui::{
     style::{Color, Style},
     widgets::{Block, Borders, Paragraph},
 };
 
-use crate::app::App;
+use super::calendar::{Calendar, DayKind};
+use super::parse::{parse_day, parse_time};
 use crate::traits::EditingPopUp;
-use crate::util::time_fmt::{DATE_FMT, TIME_FMT};
+use crate::util::{
+    self,
+    time_fmt::{DATE_FMT, TIME_FMT},
+    Zone,
+};
 
 #[derive(Debug, Clone)]
 pub struct ActivityBeingBuilt {
     id: ActivityId,
     last_time: Option<Time>,
+    /// The offset the times are typed in, `None` for the home zone.
+    offset: Option<UtcOffset>,
+    home: Zone,
+    /// Whether this builds a new activity rather than editing one.
+    new: bool,
     pub action: String,
     pub issue: String,
     pub start_time: String,
@@ -66,10 +79,16 @@ impl Selected {
 }
 
 impl ActivityBeingBuilt {
-    pub fn new(last_time: Option<Time>) -> Self {
+    pub fn new(last_time: Option<Time>, home: &Zone) -> Self {
+        let offset = home.away_offset();
+        let today = home.now().date();
         Self {
             id: ActivityId::default(),
-            last_time,
+            last_time: last_time
+                .map(|t| util::convert(today.with_time(t), home, &zone_or(offset, home)).time()),
+            offset,
+            home: home.clone(),
+            new: true,
             action: String::new(),
             issue: String::new(),
             start_time: String::default(),
@@ -79,6 +98,24 @@ impl ActivityBeingBuilt {
             editing: true,
         }
     }
+
+    /// This builder applied again on the day of `selected`. An edit is applied to `selected`
+    /// itself, a new activity is added again.
+    pub fn repeated_on(&self, selected: Option<&Activity>) -> Self {
+        let mut builder = self.clone();
+        match selected {
+            Some(a) => {
+                builder.day = a.day.format(DATE_FMT).unwrap();
+                builder.id = if self.new {
+                    ActivityId::default()
+                } else {
+                    a.id
+                };
+            }
+            None => builder.id = ActivityId::default(),
+        }
+        builder
+    }
 }
 
 impl EditingPopUp for ActivityBeingBuilt {
@@ -104,14 +141,6 @@ impl EditingPopUp for ActivityBeingBuilt {
         self.editing = state;
     }
 
-    fn submit(&self, app: &mut App) -> Result<(), &'static str> {
-        let to_submit: Activity = self.try_into()?;
-        app.add_activity(to_submit);
-        app.pop_up = None;
-        let _ = app.save_to(&app.filename);
-        Ok(())
-    }
-
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -135,12 +164,42 @@ impl EditingPopUp for ActivityBeingBuilt {
                 })
                 .block(Block::default().borders(Borders::ALL).title(title))
         };
+        // show what the times will be parsed as while they're typed
+        let preview = |title, time: &Result<Time, &str>| match time {
+            Ok(t) => format!("{}: {}", title, t.format(TIME_FMT).unwrap()),
+            Err(e) => format!("{}: {}", title, e),
+        };
+        let now = zone_or(self.offset, &self.home).now().time();
+        let start_time = parse_time(&self.start_time, now, self.last_time, None);
+        let end_title = if self.end_time.is_empty() {
+            "end time".to_string()
+        } else {
+            let end_time = parse_time(&self.end_time, now, None, start_time.ok());
+            match (start_time, end_time) {
+                (Ok(start), Ok(end)) if end < start => {
+                    format!("{} on the next day", preview("end time", &end_time))
+                }
+                _ => preview("end time", &end_time),
+            }
+        };
+        let day_title = match parse_day(&self.day) {
+            Ok(d) => format!("day: {}", d.format(DATE_FMT).unwrap()),
+            Err(e) => format!("day: {}", e),
+        };
+        let day_title = match self.offset {
+            Some(o) => format!("{} (times at UTC{})", day_title, util::fmt_offset(o)),
+            None => day_title,
+        };
         vec![
-            mkparagraph("issue", self.issue.as_str(), Selected::Issue),
-            mkparagraph("action", self.action.as_str(), Selected::Action),
-            mkparagraph("start time", &self.start_time, Selected::StartTime),
-            mkparagraph("end time", &self.end_time, Selected::EndTime),
-            mkparagraph("day", &self.day, Selected::Day),
+            mkparagraph("issue".to_string(), self.issue.as_str(), Selected::Issue),
+            mkparagraph("action".to_string(), self.action.as_str(), Selected::Action),
+            mkparagraph(
+                preview("start time", &start_time),
+                &self.start_time,
+                Selected::StartTime,
+            ),
+            mkparagraph(end_title, &self.end_time, Selected::EndTime),
+            mkparagraph(day_title, &self.day, Selected::Day),
         ]
     }
 
@@ -149,19 +208,25 @@ impl EditingPopUp for ActivityBeingBuilt {
     }
 }
 
-impl From<(&Activity, Option<Time>)> for ActivityBeingBuilt {
-    fn from((a, last_time): (&Activity, Option<Time>)) -> Self {
+impl From<(&Activity, Option<Time>, &Zone)> for ActivityBeingBuilt {
+    fn from((a, last_time, home): (&Activity, Option<Time>, &Zone)) -> Self {
+        // activities are edited in the offset they were recorded in
+        let zone = zone_or(a.offset, home);
+        let local = a.converted(home, &zone);
         Self {
             id: a.id,
-            last_time,
+            last_time: last_time.map(|t| util::convert(a.day.with_time(t), home, &zone).time()),
+            offset: a.offset,
+            home: home.clone(),
+            new: false,
             action: a.action.clone(),
             issue: a.issue.clone(),
-            start_time: a.start_time.format(TIME_FMT).unwrap(),
-            end_time: a
+            start_time: local.start_time.format(TIME_FMT).unwrap(),
+            end_time: local
                 .end_time
                 .map(|t| t.format(TIME_FMT).unwrap())
                 .unwrap_or_default(),
-            day: a.day.format(DATE_FMT).unwrap(),
+            day: local.day.format(DATE_FMT).unwrap(),
             selected: Selected::Action,
             editing: true,
         }
@@ -175,28 +240,36 @@ impl TryFrom<&ActivityBeingBuilt> for Activity {
         if builder.action.is_empty() {
             return Err("action field is mandatory");
         }
-        let start_time = parse_time(&builder.start_time, true, builder.last_time)?;
-        Ok(Activity {
+        let zone = zone_or(builder.offset, &builder.home);
+        let now = zone.now().time();
+        let start_time = parse_time(&builder.start_time, now, builder.last_time, None)?;
+        let activity = Activity {
             id: builder.id,
             start_time,
+            // an end time before the start time is on the next day
             end_time: if builder.end_time.is_empty() {
                 None
             } else {
-                let end_time = parse_time(&builder.end_time, false, None)?;
-                if end_time < start_time {
-                    return Err("end time can't be before start time");
-                } else {
-                    Some(end_time)
-                }
+                Some(parse_time(&builder.end_time, now, None, Some(start_time))?)
             },
             day: parse_day(&builder.day)?,
             action: builder.action.clone(),
             issue: builder.issue.clone(),
+            offset: builder.offset,
             _m: PhantomData,
-        })
+        };
+        match builder.offset {
+            Some(_) => Ok(activity.converted(&zone, &builder.home)),
+            None => Ok(activity),
+        }
     }
 }
 
+/// The zone of the wall clock at `offset`, or `home` when there's none.
+fn zone_or(offset: Option<UtcOffset>, home: &Zone) -> Zone {
+    offset.map_or_else(|| home.clone(), Zone::fixed)
+}
+
 impl TryFrom<&mut ActivityBeingBuilt> for Activity {
     type Error = &'static str;
 
@@ -205,96 +278,37 @@ impl TryFrom<&mut ActivityBeingBuilt> for Activity {
     }
 }
 
-fn parse_time(s: &str, assume_now: bool, last_time: Option<Time>) -> Result<Time, &'static str> {
-    let now = OffsetDateTime::now_local()
-        .map(OffsetDateTime::time)
-        .map_err(|_| "The system's UTC offset could not be determined")?;
-    if s.eq_ignore_ascii_case("now") || (s.is_empty() && assume_now) {
-        return Ok(Time::from_hms(now.hour(), now.minute(), 0).unwrap());
-    } else if s.eq_ignore_ascii_case("last") {
-        if let Some(last_time) = last_time {
-            return Ok(last_time);
-        } else {
-            return Err("no previous time available");
-        }
+/// Identifies an activity across sessions, it's stored in the `id` column of the activities file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ActivityId(Uuid);
+
+impl Default for ActivityId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
     }
-    let (hour, minute) = s.split_once(':').unwrap_or((s, ""));
-    let hour = hour
-        .parse()
-        .map_err(|_| "failed to parse time: invalid hour")?;
-    let minute = if minute.is_empty() {
-        if hour == now.hour() {
-            now.minute()
-        } else {
-            return Err("can't use current minute because you are not inputing current hour");
-        }
-    } else {
-        minute
-            .parse()
-            .map_err(|_| "failed to parse time: invalid minute")?
-    };
-
-    Time::from_hms(hour, minute, 0)
-        .map_err(|_| "failed to parse time: hour or minute out of bounds")
 }
 
-pub fn parse_day(s: &str) -> Result<Date, &'static str> {
-    let mut today = OffsetDateTime::now_local()
-        .unwrap_or_else(|_| OffsetDateTime::now_utc())
-        .date();
-    let mut iter = s.split(&['/', '-'][..]);
-    if let Some(day) = iter.next().map(|s| s.trim()).filter(|s| !s.is_empty()) {
-        let day = day
-            .trim()
-            .parse()
-            .map_err(|_| "failed to parse date: invalid day")?;
-        today = Date::from_calendar_date(today.year(), today.month(), day)
-            .map_err(|_| "failed to parse date: day out of bounds")?;
-    }
-
-    if let Some(month) = iter.next() {
-        let month = match month
-            .trim()
-            .parse()
-            .map_err(|_| "failed to parse date: invalid month number")?
-        {
-            1 => Month::January,
-            2 => Month::February,
-            3 => Month::March,
-            4 => Month::April,
-            5 => Month::May,
-            6 => Month::June,
-            7 => Month::July,
-            8 => Month::August,
-            9 => Month::September,
-            10 => Month::October,
-            11 => Month::November,
-            12 => Month::December,
-            _ => return Err("failed to parse date: month number out of bounds"),
-        };
-        today = Date::from_calendar_date(today.year(), month, today.day())
-            .map_err(|_| "failed to parse date: invalid month")?;
-    }
-    if let Some(year) = iter.next() {
-        let year = year
-            .trim()
-            .parse()
-            .map_err(|_| "failed to parse date: invalid year")?;
-        today = Date::from_calendar_date(year, today.month(), today.day())
-            .map_err(|_| "failed to parse date: year out of bounds")?;
+impl fmt::Display for ActivityId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
     }
-
-    Ok(today)
 }
 
-static ID: AtomicUsize = AtomicUsize::new(0);
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct ActivityId(usize);
+impl Serialize for ActivityId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
 
-impl Default for ActivityId {
-    fn default() -> Self {
-        Self(ID.fetch_add(1, Ordering::Relaxed))
+impl<'de> Deserialize<'de> for ActivityId {
+    /// Rows written before ids were stored have an empty id, they get a new one.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        if s.is_empty() {
+            Ok(Self::default())
+        } else {
+            Uuid::parse_str(&s).map(Self).map_err(de::Error::custom)
+        }
     }
 }
 
@@ -305,35 +319,163 @@ pub struct Activity {
     pub end_time: Option<Time>,
     pub action: String,
     pub issue: String,
-    #[serde(skip_serializing, skip_deserializing, default)]
+    #[serde(default)]
     pub id: ActivityId,
+    /// The offset of the time zone the activity was recorded in when it isn't the home one. The
+    /// file stores the times at that offset, in memory they're at the home one.
+    #[serde(default, with = "util::offset_fmt")]
+    pub offset: Option<UtcOffset>,
     #[serde(skip)]
     _m: PhantomData<()>, // prevent constructing this type outside this module
 }
 
-pub fn load_activities<P: AsRef<Path>>(path: P) -> io::Result<Vec<Activity>> {
-    match File::open(&path) {
-        Ok(f) => {
-            let file = BufReader::new(f);
-            Ok(csv::Reader::from_reader(file)
-                .deserialize::<Activity>()
-                .collect::<Result<Vec<_>, _>>()?)
+impl Activity {
+    /// Creates an activity that starts at `start` and is still running.
+    pub fn start_at(start: PrimitiveDateTime, action: String, issue: String) -> Self {
+        Self {
+            day: start.date(),
+            start_time: Time::from_hms(start.hour(), start.minute(), 0).unwrap(),
+            end_time: None,
+            action,
+            issue,
+            id: ActivityId::default(),
+            offset: None,
+            _m: PhantomData,
+        }
+    }
+
+    /// This activity with its times moved from the wall clock in `from` to the one in `to`.
+    pub fn converted(&self, from: &Zone, to: &Zone) -> Self {
+        let start = util::convert(self.start(), from, to);
+        Self {
+            day: start.date(),
+            start_time: start.time(),
+            end_time: self.end().map(|end| util::convert(end, from, to).time()),
+            ..self.clone()
+        }
+    }
+
+    pub fn start(&self) -> PrimitiveDateTime {
+        self.day.with_time(self.start_time)
+    }
+
+    /// Whether this activity ended on the day after it started, which is the case when its end
+    /// time is before its start time.
+    pub fn crosses_midnight(&self) -> bool {
+        self.end_time.is_some_and(|end| end < self.start_time)
+    }
+
+    pub fn end(&self) -> Option<PrimitiveDateTime> {
+        let end = self.end_time?;
+        if self.crosses_midnight() {
+            Some(self.day.next_day()?.with_time(end))
+        } else {
+            Some(self.day.with_time(end))
+        }
+    }
+
+    /// Whether this activity hasn't been stopped yet and was started less than a day ago.
+    pub fn is_running(&self, now: PrimitiveDateTime) -> bool {
+        self.end_time.is_none() && self.start() <= now && now - self.start() < Duration::DAY
+    }
+
+    /// When this activity ended, running activities end `now`.
+    fn end_or_now(&self, now: PrimitiveDateTime) -> Option<PrimitiveDateTime> {
+        match self.end() {
+            Some(end) => Some(end),
+            None if self.is_running(now) => Some(now),
+            None => None,
         }
+    }
+
+    /// The time spent on this activity. Running activities are counted up to `now`.
+    pub fn time_spent(&self, now: PrimitiveDateTime) -> Option<Duration> {
+        Some(self.end_or_now(now)? - self.start())
+    }
+
+    /// The part of the time spent on this activity that falls on `day`.
+    pub fn time_spent_on(&self, day: Date, now: PrimitiveDateTime) -> Option<Duration> {
+        let end = self.end_or_now(now)?.min(day.next_day()?.midnight());
+        let start = self.start().max(day.midnight());
+        Some((end - start).max(Duration::ZERO))
+    }
+}
+
+/// Load the activities file, the times of activities recorded away from `home` are moved to its
+/// wall clock. Rows without an offset are already at home, whichever zone it is.
+pub fn load_activities<P: AsRef<Path>>(path: P, home: &Zone) -> io::Result<Vec<Activity>> {
+    match File::open(&path) {
+        Ok(f) => read_activities(BufReader::new(f), home),
         Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
         Err(e) => Err(e),
     }
 }
 
+fn read_activities<R: Read>(reader: R, home: &Zone) -> io::Result<Vec<Activity>> {
+    let mut seen = HashSet::new();
+    csv::Reader::from_reader(reader)
+        .deserialize::<Activity>()
+        .map(|a| {
+            let mut a = a?;
+            // rows copied by hand would otherwise replace each other
+            while !seen.insert(a.id) {
+                a.id = ActivityId::default();
+            }
+            match a.offset {
+                Some(offset) => Ok(a.converted(&Zone::fixed(offset), home)),
+                None => Ok(a),
+            }
+        })
+        .collect()
+}
+
 const FMT: &[FormatItem<'static>] = format_description!("[year]-[month]-[day]");
 
-pub fn load_days_off<P: AsRef<Path>>(path: P) -> io::Result<Vec<Date>> {
-    load_list_dates(format!("{}-off", path.as_ref().display()))
+/// Days off are stored one per line, optionally followed by their kind. Days without a kind are
+/// vacation days.
+pub fn load_days_off<P: AsRef<Path>>(path: P) -> io::Result<Vec<(Date, DayKind)>> {
+    match File::open(format!("{}-off", path.as_ref().display())) {
+        Ok(mut f) => {
+            let mut s = String::new();
+            f.read_to_string(&mut s)?;
+            s.split('\n')
+                .filter(|l| !l.is_empty())
+                .map(|l| {
+                    let (date, kind) = l.split_once(' ').unwrap_or((l, "vacation"));
+                    let date = Date::parse(date, FMT)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    let kind = kind
+                        .trim()
+                        .parse()
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    Ok((date, kind))
+                })
+                .collect()
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e),
+    }
 }
 
 pub fn load_holidays<P: AsRef<Path>>(path: P) -> io::Result<Vec<Date>> {
     load_list_dates(format!("{}-holidays", path.as_ref().display()))
 }
 
+/// The days off and holidays of an activities file.
+pub fn load_calendar<P: AsRef<Path>>(path: P) -> io::Result<Calendar> {
+    let path = path.as_ref();
+    Calendar::new(load_days_off(path)?, load_holidays(path)?).map_err(|date| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} is both a day off and a holiday in {}, remove it from one of them",
+                date.format(FMT).unwrap(),
+                path.display()
+            ),
+        )
+    })
+}
+
 pub fn load_list_dates<P: AsRef<Path>>(path: P) -> io::Result<Vec<Date>> {
     match File::open(path) {
         Ok(mut f) => {
@@ -353,7 +495,7 @@ pub fn load_list_dates<P: AsRef<Path>>(path: P) -> io::Result<Vec<Date>> {
     }
 }
 
-pub fn store_activities<'a, I, W>(writer: W, activities: I) -> io::Result<()>
+pub fn store_activities<'a, I, W>(writer: W, activities: I, home: &Zone) -> io::Result<()>
 where
     I: Iterator<Item = &'a Activity>,
     W: Write,
@@ -361,9 +503,33 @@ where
     let file = BufWriter::new(writer);
     let mut writer = csv::Writer::from_writer(file);
     for a in activities {
-        writer.serialize(a)?;
+        match a.offset {
+            Some(offset) => writer.serialize(a.converted(home, &Zone::fixed(offset)))?,
+            None => writer.serialize(a)?,
+        }
     }
-    Ok(())
+    writer.flush()
+}
+
+pub fn store_days_off<'a, I, W>(writer: W, days_off: I) -> io::Result<()>
+where
+    I: Iterator<Item = (&'a Date, DayKind)>,
+    W: Write,
+{
+    use time::error::Format;
+    let mut writer = BufWriter::new(writer);
+    for (d, kind) in days_off {
+        match d.format_into(&mut writer, FMT) {
+            Ok(_) => {}
+            Err(Format::StdIo(e)) => return Err(e),
+            Err(e) => panic!("{}", e),
+        }
+        if kind != DayKind::Vacation {
+            write!(writer, " {}", kind.as_str())?;
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()
 }
 
 pub fn store_list_dates<'a, I, W>(writer: W, days_off: I) -> io::Result<()>
@@ -381,5 +547,129 @@ where
         }
         writeln!(writer)?;
     }
-    Ok(())
+    writer.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use time::macros::{date, datetime, time};
+
+    use super::*;
+
+    #[test]
+    fn rows_without_ids_get_one() {
+        let csv = "day,start_time,end_time,action,issue\n\
+                   2022-01-03,09:00:00.0,10:00:00.0,coding,EF-1\n\
+                   2022-01-03,10:00:00.0,11:00:00.0,review,EF-2\n";
+        let acts = read_activities(csv.as_bytes(), &Zone::default()).unwrap();
+        assert_eq!(acts.len(), 2);
+        assert_ne!(acts[0].id, acts[1].id);
+    }
+
+    #[test]
+    fn ids_are_kept() {
+        let id = "7c1e3a5e-2f1b-4c55-9d43-2f8a0f9c9e10";
+        let csv = format!(
+            "day,start_time,end_time,action,issue,id\n\
+             2022-01-03,09:00:00.0,10:00:00.0,coding,EF-1,{}\n\
+             2022-01-03,10:00:00.0,11:00:00.0,review,EF-2,\n",
+            id
+        );
+        let acts = read_activities(csv.as_bytes(), &Zone::default()).unwrap();
+        assert_eq!(acts[0].id.to_string(), id);
+        let mut out = Vec::new();
+        store_activities(&mut out, acts.iter(), &Zone::default()).unwrap();
+        assert_eq!(
+            read_activities(out.as_slice(), &Zone::default()).unwrap(),
+            acts
+        );
+    }
+
+    #[test]
+    fn duplicated_ids_are_replaced() {
+        let row =
+            "2022-01-03,09:00:00.0,10:00:00.0,coding,EF-1,7c1e3a5e-2f1b-4c55-9d43-2f8a0f9c9e10";
+        let csv = format!(
+            "day,start_time,end_time,action,issue,id\n{}\n{}\n",
+            row, row
+        );
+        let acts = read_activities(csv.as_bytes(), &Zone::default()).unwrap();
+        assert_ne!(acts[0].id, acts[1].id);
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(
+            util::parse_offset("+05:30").unwrap(),
+            UtcOffset::from_hms(5, 30, 0).unwrap()
+        );
+        assert_eq!(
+            util::parse_offset("-3").unwrap(),
+            UtcOffset::from_hms(-3, 0, 0).unwrap()
+        );
+        assert_eq!(util::parse_offset("UTC"), Ok(UtcOffset::UTC));
+        assert!(util::parse_offset("5").is_err());
+        assert_eq!(
+            util::fmt_offset(UtcOffset::from_hms(-9, -30, 0).unwrap()),
+            "-09:30"
+        );
+    }
+
+    #[test]
+    fn converted_across_days() {
+        let mut a = Activity::start_at(datetime!(2022-01-03 22:30), "flight".into(), "".into());
+        a.end_time = Some(time!(23:30));
+        let home = Zone::fixed(UtcOffset::from_hms(1, 0, 0).unwrap());
+        let away = Zone::fixed(UtcOffset::from_hms(5, 30, 0).unwrap());
+        let local = a.converted(&home, &away);
+        assert_eq!(local.start(), datetime!(2022-01-04 3:00));
+        assert_eq!(local.end(), Some(datetime!(2022-01-04 4:00)));
+        assert_eq!(local.converted(&away, &home), a);
+    }
+
+    #[test]
+    fn daylight_saving_time() {
+        let home = Zone::parse("Europe/Berlin").unwrap();
+        let summer = UtcOffset::from_hms(2, 0, 0).unwrap();
+        assert_eq!(home.offset_at(datetime!(2022-07-01 12:00 UTC)), summer);
+        assert_eq!(
+            home.offset_at(datetime!(2022-01-01 12:00 UTC)),
+            UtcOffset::from_hms(1, 0, 0).unwrap()
+        );
+        // recorded at home in the summer, the times don't move
+        let mut a = Activity::start_at(datetime!(2022-07-01 9:00), "coding".into(), "".into());
+        a.end_time = Some(time!(17:00));
+        assert_eq!(a.converted(&Zone::fixed(summer), &home), a);
+    }
+
+    #[test]
+    fn crossing_midnight() {
+        let mut a = Activity::start_at(datetime!(2022-01-03 22:30), "deploy".into(), "".into());
+        a.end_time = Some(time!(1:00));
+        let now = datetime!(2022-01-10 12:00);
+        assert!(a.crosses_midnight());
+        assert_eq!(a.end(), Some(datetime!(2022-01-04 1:00)));
+        assert_eq!(a.time_spent(now), Some(Duration::minutes(150)));
+        assert_eq!(
+            a.time_spent_on(date!(2022 - 01 - 03), now),
+            Some(Duration::minutes(90))
+        );
+        assert_eq!(
+            a.time_spent_on(date!(2022 - 01 - 04), now),
+            Some(Duration::hours(1))
+        );
+        assert_eq!(
+            a.time_spent_on(date!(2022 - 01 - 05), now),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn running_across_midnight() {
+        let a = Activity::start_at(datetime!(2022-01-03 23:00), "deploy".into(), "".into());
+        let now = datetime!(2022-01-04 0:30);
+        assert!(a.is_running(now));
+        assert_eq!(a.time_spent(now), Some(Duration::minutes(90)));
+        assert!(!a.is_running(datetime!(2022-01-05 0:30)));
+    }
 }
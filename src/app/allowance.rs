@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use time::{Date, Month};
+
+use super::calendar::DayKind;
+
+/// How many vacation days can be taken each year.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct VacationPolicy {
+    pub yearly_days: f32,
+    /// Earn the yearly days month by month instead of all of them on the first of January.
+    #[serde(default)]
+    pub monthly_accrual: bool,
+    /// How many unused days can be carried over into the next year, unlimited if not set.
+    #[serde(default)]
+    pub max_carry_over: Option<f32>,
+    /// The day of the year after which the days carried over that weren't used are lost.
+    #[serde(default)]
+    pub carry_over_expiry: Option<MonthDay>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct MonthDay {
+    pub month: u8,
+    pub day: u8,
+}
+
+impl MonthDay {
+    fn in_year(self, year: i32) -> Option<Date> {
+        let month = Month::try_from(self.month).ok()?;
+        Date::from_calendar_date(year, month, self.day).ok()
+    }
+}
+
+/// The state of the vacation allowance of a year.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Allowance {
+    pub year: i32,
+    /// The days earned this year so far.
+    pub accrued: f32,
+    pub carried_over: f32,
+    /// When the days carried over expire, if they do.
+    pub expires: Option<Date>,
+    /// The days carried over that were lost because they weren't used in time.
+    pub expired: f32,
+    /// The days taken up to today.
+    pub used: f32,
+    /// The days taken after today.
+    pub planned: f32,
+}
+
+impl Allowance {
+    /// The days still left after the used and planned ones.
+    pub fn remaining(&self) -> f32 {
+        self.accrued + self.carried_over - self.expired - self.used - self.planned
+    }
+
+    fn carry_over(&self, policy: &VacationPolicy) -> f32 {
+        let unused = self.remaining().max(0.0);
+        match policy.max_carry_over {
+            Some(max) => unused.min(max),
+            None => unused,
+        }
+    }
+}
+
+impl VacationPolicy {
+    /// The allowance of the year `today` is in, carrying over what's left from the years before.
+    ///
+    /// The first year considered is the year of the earliest day off.
+    pub fn allowance(&self, days_off: &[(Date, DayKind)], today: Date) -> Allowance {
+        let first_year = days_off
+            .iter()
+            .map(|(d, _)| d.year())
+            .min()
+            .unwrap_or(today.year())
+            .min(today.year());
+        let mut carried_over = 0.0;
+        let mut allowance = Allowance::default();
+        for year in first_year..=today.year() {
+            allowance = self.year_allowance(year, carried_over, days_off, today);
+            carried_over = allowance.carry_over(self);
+        }
+        allowance
+    }
+
+    fn year_allowance(
+        &self,
+        year: i32,
+        carried_over: f32,
+        days_off: &[(Date, DayKind)],
+        today: Date,
+    ) -> Allowance {
+        let days = days_off
+            .iter()
+            .filter(|(d, _)| d.year() == year)
+            .map(|(d, kind)| (*d, kind.allowance_used()));
+        let accrued = if self.monthly_accrual && year == today.year() {
+            self.yearly_days * f32::from(u8::from(today.month())) / 12.0
+        } else {
+            self.yearly_days
+        };
+        let expires = self.carry_over_expiry.and_then(|e| e.in_year(year));
+        let expired = match expires {
+            Some(expires) if expires < today => {
+                let used_in_time = days
+                    .clone()
+                    .filter(|(d, _)| *d <= expires)
+                    .map(|(_, n)| n)
+                    .sum::<f32>();
+                (carried_over - used_in_time).max(0.0)
+            }
+            _ => 0.0,
+        };
+        let (used, planned) = days.fold((0.0, 0.0), |(used, planned), (d, n)| {
+            if d <= today {
+                (used + n, planned)
+            } else {
+                (used, planned + n)
+            }
+        });
+        Allowance {
+            year,
+            accrued,
+            carried_over,
+            expires,
+            expired,
+            used,
+            planned,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use time::macros::date;
+
+    use super::*;
+
+    fn policy() -> VacationPolicy {
+        VacationPolicy {
+            yearly_days: 24.0,
+            monthly_accrual: false,
+            max_carry_over: Some(5.0),
+            carry_over_expiry: Some(MonthDay { month: 3, day: 31 }),
+        }
+    }
+
+    #[test]
+    fn used_and_planned() {
+        let days = [
+            (date!(2022 - 02 - 01), DayKind::Vacation),
+            (date!(2022 - 02 - 02), DayKind::HalfDay),
+            (date!(2022 - 02 - 03), DayKind::SickLeave),
+            (date!(2022 - 08 - 01), DayKind::Vacation),
+        ];
+        let a = policy().allowance(&days, date!(2022 - 06 - 01));
+        assert_eq!(a.used, 1.5);
+        assert_eq!(a.planned, 1.0);
+        assert_eq!(a.remaining(), 21.5);
+    }
+
+    #[test]
+    fn monthly_accrual() {
+        let policy = VacationPolicy {
+            monthly_accrual: true,
+            ..policy()
+        };
+        let a = policy.allowance(&[], date!(2022 - 03 - 15));
+        assert_eq!(a.accrued, 6.0);
+    }
+
+    #[test]
+    fn carry_over_is_capped() {
+        let days = [(date!(2021 - 02 - 01), DayKind::Vacation)];
+        let a = policy().allowance(&days, date!(2022 - 02 - 01));
+        assert_eq!(a.carried_over, 5.0);
+        assert_eq!(a.remaining(), 29.0);
+    }
+
+    #[test]
+    fn carry_over_expires() {
+        let days = [
+            (date!(2021 - 02 - 01), DayKind::Vacation),
+            (date!(2022 - 02 - 01), DayKind::Vacation),
+            (date!(2022 - 02 - 02), DayKind::Vacation),
+        ];
+        let a = policy().allowance(&days, date!(2022 - 06 - 01));
+        assert_eq!(a.expired, 3.0);
+        assert_eq!(a.remaining(), 24.0);
+    }
+}
@@ -0,0 +1,102 @@
+use std::{
+    cmp::Reverse,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use time::{format_description::FormatItem, macros::format_description, PrimitiveDateTime};
+
+use crate::util;
+
+/// The suffixes of the files that make up the data of an activities file.
+const FILES: [&str; 5] = ["", "-off", "-holidays", "-balance", "-history"];
+
+const STAMP_FMT: &[FormatItem<'static>] =
+    format_description!("[year][month][day]T[hour][minute][second].[subsecond digits:3]");
+
+/// A copy of an activities file and its side files, kept in a directory of its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    pub name: String,
+    pub taken: PrimitiveDateTime,
+    pub path: PathBuf,
+}
+
+impl Backup {
+    /// The copy of the activities file in this backup.
+    pub fn data_file(&self, filename: &str) -> PathBuf {
+        self.path.join(file_name(filename))
+    }
+}
+
+fn file_name(filename: &str) -> String {
+    Path::new(filename)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| filename.to_string())
+}
+
+/// What the names of the backups of the file start with. Files with the same name in different
+/// directories share the backup directory, so the name is followed by a hash of the full path.
+fn prefix(filename: &str) -> String {
+    let path = fs::canonicalize(filename)
+        .or_else(|_| std::path::absolute(filename))
+        .unwrap_or_else(|_| PathBuf::from(filename));
+    // FNV-1a, the names have to stay the same across builds
+    let hash = path
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf29ce484222325_u64, |h, b| {
+            (h ^ u64::from(b)).wrapping_mul(0x100000001b3)
+        });
+    format!("{}-{:016x}-", file_name(filename), hash)
+}
+
+/// Copy the activities file and its side files into a new backup in `dir`, keeping only the
+/// `keep` most recent backups of this file.
+pub fn backup(filename: &str, dir: &Path, keep: usize) -> io::Result<()> {
+    if keep == 0 || !Path::new(filename).exists() {
+        return Ok(());
+    }
+    let name = file_name(filename);
+    let stamp = util::now().format(STAMP_FMT).unwrap();
+    let path = dir.join(format!("{}{}", prefix(filename), stamp));
+    fs::create_dir_all(&path)?;
+    for suffix in FILES {
+        let file = format!("{}{}", filename, suffix);
+        if Path::new(&file).exists() {
+            fs::copy(&file, path.join(format!("{}{}", name, suffix)))?;
+        }
+    }
+    for old in list_backups(filename, dir)?.into_iter().skip(keep) {
+        fs::remove_dir_all(old.path)?;
+    }
+    Ok(())
+}
+
+/// The backups of the activities file in `dir`, most recent first.
+pub fn list_backups(filename: &str, dir: &Path) -> io::Result<Vec<Backup>> {
+    let prefix = prefix(filename);
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let mut backups = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let taken = name
+            .strip_prefix(&prefix)
+            .and_then(|stamp| PrimitiveDateTime::parse(stamp, STAMP_FMT).ok());
+        if let Some(taken) = taken {
+            backups.push(Backup {
+                name,
+                taken,
+                path: entry.path(),
+            });
+        }
+    }
+    backups.sort_unstable_by_key(|b| Reverse(b.taken));
+    Ok(backups)
+}
@@ -0,0 +1,168 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use time::{Date, Duration, PrimitiveDateTime};
+
+use super::{
+    activity::{load_activities, load_calendar},
+    config::Config,
+    state::State,
+    summary::Summary,
+};
+
+/// The flextime ledger of an activities file, stored next to it in `<file>-balance`.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Ledger {
+    /// The activities file of the previous period, its closing balance is carried over. Relative
+    /// paths are relative to the directory of the activities file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+    /// The balance, in hours, this file starts with. Takes precedence over `previous`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opening: Option<f32>,
+    #[serde(default)]
+    pub adjustments: Vec<Adjustment>,
+}
+
+/// A manual change to the balance, like a payout or a correction.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Adjustment {
+    pub day: Date,
+    pub hours: f32,
+    #[serde(default)]
+    pub note: String,
+}
+
+/// The overtime balance at the end of the period covered by an activities file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Balance {
+    pub opening: Duration,
+    pub overtime: Duration,
+    pub adjustments: Duration,
+}
+
+impl Balance {
+    pub fn closing(&self) -> Duration {
+        self.opening + self.overtime + self.adjustments
+    }
+}
+
+fn hours(h: f32) -> Duration {
+    Duration::seconds_f32(h * 3600.0)
+}
+
+impl Ledger {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The balance the activities file at `path` opens with, reading the files it's chained to.
+    pub fn opening_balance(
+        &self,
+        path: &Path,
+        config: &Config,
+        now: PrimitiveDateTime,
+    ) -> io::Result<Duration> {
+        let mut visited = path.canonicalize().into_iter().collect();
+        self.opening_chained(path, config, now, &mut visited)
+    }
+
+    /// The balance of an activities file that opens with `opening` and whose own overtime is
+    /// `summary`.
+    pub fn balance(&self, opening: Duration, summary: &Summary) -> Balance {
+        Balance {
+            opening,
+            overtime: summary.overtime(),
+            adjustments: self.adjustments.iter().map(|a| hours(a.hours)).sum(),
+        }
+    }
+
+    /// The activities files this one is chained to, most recent first.
+    pub fn previous_files(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut visited: Vec<PathBuf> = path.canonicalize().into_iter().collect();
+        let mut files = Vec::new();
+        let mut current = (path.to_path_buf(), self.previous.clone());
+        while let (path, Some(previous)) = current {
+            let previous = path.parent().unwrap_or(Path::new("")).join(previous);
+            visit(&previous, &mut visited)?;
+            current = (previous.clone(), load_ledger(&previous)?.previous);
+            files.push(previous);
+        }
+        Ok(files)
+    }
+
+    fn opening_chained(
+        &self,
+        path: &Path,
+        config: &Config,
+        now: PrimitiveDateTime,
+        visited: &mut Vec<PathBuf>,
+    ) -> io::Result<Duration> {
+        match (self.opening, &self.previous) {
+            (Some(opening), _) => Ok(hours(opening)),
+            (None, Some(previous)) => {
+                let previous = path.parent().unwrap_or(Path::new("")).join(previous);
+                closing_balance(&previous, config, now, visited)
+            }
+            (None, None) => Ok(Duration::ZERO),
+        }
+    }
+}
+
+fn closing_balance(
+    path: &Path,
+    config: &Config,
+    now: PrimitiveDateTime,
+    visited: &mut Vec<PathBuf>,
+) -> io::Result<Duration> {
+    visit(path, visited)?;
+    let activities: State = load_activities(path, &config.home_zone)?
+        .into_iter()
+        .collect();
+    let calendar = load_calendar(path)?;
+    let summary = Summary::new(&activities, &calendar, config, now);
+    let ledger = load_ledger(path)?;
+    let opening = ledger.opening_chained(path, config, now, visited)?;
+    Ok(ledger.balance(opening, &summary).closing())
+}
+
+/// Add the previous file at `path` to the files `visited` while following the chain, failing if
+/// it was visited already.
+fn visit(path: &Path, visited: &mut Vec<PathBuf>) -> io::Result<()> {
+    let canonical = path.canonicalize().map_err(|e| {
+        io::Error::new(e.kind(), format!("previous file {}: {}", path.display(), e))
+    })?;
+    if visited.contains(&canonical) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("the balance chain loops back to {}", path.display()),
+        ));
+    }
+    visited.push(canonical);
+    Ok(())
+}
+
+pub fn load_ledger<P: AsRef<Path>>(path: P) -> io::Result<Ledger> {
+    match File::open(format!("{}-balance", path.as_ref().display())) {
+        Ok(mut f) => {
+            let mut s = String::new();
+            f.read_to_string(&mut s)?;
+            serde_json::from_str(&s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Ledger::default()),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn store_ledger<W>(writer: W, ledger: &Ledger) -> io::Result<()>
+where
+    W: Write,
+{
+    let mut file = BufWriter::new(writer);
+    serde_json::to_writer_pretty(&mut file, ledger)?;
+    file.flush()
+}
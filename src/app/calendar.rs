@@ -0,0 +1,170 @@
+use std::{cmp::Reverse, collections::BTreeMap, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use time::Date;
+
+/// What kind of day a date in the calendar is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DayKind {
+    Holiday,
+    Vacation,
+    SickLeave,
+    UnpaidLeave,
+    HalfDay,
+    CompTime,
+}
+
+impl DayKind {
+    pub const DAYS_OFF: [DayKind; 5] = [
+        DayKind::Vacation,
+        DayKind::SickLeave,
+        DayKind::UnpaidLeave,
+        DayKind::HalfDay,
+        DayKind::CompTime,
+    ];
+
+    /// How much of a day is taken off.
+    pub fn fraction(self) -> f32 {
+        match self {
+            Self::HalfDay => 0.5,
+            _ => 1.0,
+        }
+    }
+
+    /// How much of a day is not expected to be worked. Compensation days still count as work
+    /// days, they are paid for with overtime.
+    pub fn credit(self) -> f32 {
+        match self {
+            Self::CompTime => 0.0,
+            _ => self.fraction(),
+        }
+    }
+
+    /// How much of the vacation allowance is used by taking a day of this kind off.
+    pub fn allowance_used(self) -> f32 {
+        match self {
+            Self::Vacation | Self::HalfDay => self.fraction(),
+            _ => 0.0,
+        }
+    }
+
+    /// The next kind of day off, used to cycle through them.
+    pub fn next_day_off(self) -> Self {
+        match self {
+            Self::Holiday => Self::Holiday,
+            Self::Vacation => Self::SickLeave,
+            Self::SickLeave => Self::UnpaidLeave,
+            Self::UnpaidLeave => Self::HalfDay,
+            Self::HalfDay => Self::CompTime,
+            Self::CompTime => Self::Vacation,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Holiday => "holiday",
+            Self::Vacation => "vacation",
+            Self::SickLeave => "sick",
+            Self::UnpaidLeave => "unpaid",
+            Self::HalfDay => "half",
+            Self::CompTime => "comp",
+        }
+    }
+}
+
+impl fmt::Display for DayKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DayKind {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Self::Holiday]
+            .into_iter()
+            .chain(Self::DAYS_OFF)
+            .find(|k| s.eq_ignore_ascii_case(k.as_str()))
+            .ok_or("unknown kind of day, expected one of vacation, sick, unpaid, half or comp")
+    }
+}
+
+/// A subset of the calendar, shown in its own pop up and stored in its own file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DateList {
+    DaysOff,
+    Holidays,
+}
+
+impl DateList {
+    fn contains(self, kind: DayKind) -> bool {
+        match self {
+            Self::DaysOff => kind != DayKind::Holiday,
+            Self::Holidays => kind == DayKind::Holiday,
+        }
+    }
+}
+
+/// The days that aren't regular work days.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Calendar(BTreeMap<Reverse<Date>, DayKind>);
+
+impl Calendar {
+    /// Fails with the first date that is both a day off and a holiday, neither can be dropped
+    /// without losing what the other file says.
+    pub fn new(days_off: Vec<(Date, DayKind)>, holidays: Vec<Date>) -> Result<Self, Date> {
+        let mut calendar = Self(days_off.into_iter().map(|(d, k)| (Reverse(d), k)).collect());
+        for date in holidays {
+            match calendar.get(date) {
+                Some(DayKind::Holiday) | None => {
+                    calendar.insert(date, DayKind::Holiday);
+                }
+                Some(_) => return Err(date),
+            }
+        }
+        Ok(calendar)
+    }
+
+    pub fn get(&self, date: Date) -> Option<DayKind> {
+        self.0.get(&Reverse(date)).copied()
+    }
+
+    /// Returns the kind the date had before.
+    pub fn insert(&mut self, date: Date, kind: DayKind) -> Option<DayKind> {
+        self.0.insert(Reverse(date), kind)
+    }
+
+    /// Returns the kind the date had.
+    pub fn remove(&mut self, date: Date) -> Option<DayKind> {
+        self.0.remove(&Reverse(date))
+    }
+
+    /// The dates in the list, most recent first.
+    pub fn iter(&self, list: DateList) -> impl DoubleEndedIterator<Item = (&Date, DayKind)> {
+        self.0
+            .iter()
+            .filter(move |(_, k)| list.contains(**k))
+            .map(|(d, k)| (&d.0, *k))
+    }
+
+    /// Every date in the calendar, most recent first.
+    pub fn all(&self) -> impl DoubleEndedIterator<Item = (&Date, DayKind)> {
+        self.0.iter().map(|(d, k)| (&d.0, *k))
+    }
+
+    pub fn len(&self, list: DateList) -> usize {
+        self.iter(list).count()
+    }
+
+    pub fn is_empty(&self, list: DateList) -> bool {
+        self.iter(list).next().is_none()
+    }
+}
+
+impl FromIterator<(Date, DayKind)> for Calendar {
+    fn from_iter<I: IntoIterator<Item = (Date, DayKind)>>(iter: I) -> Self {
+        Self(iter.into_iter().map(|(d, k)| (Reverse(d), k)).collect())
+    }
+}
@@ -0,0 +1,190 @@
+use std::str::FromStr;
+
+use time::Date;
+
+use super::calendar::DayKind;
+use super::parse::parse_day;
+
+/// A command typed on the command line opened with `:`, like in vim.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExCommand {
+    /// `:w [file]` saves, or writes a copy to another file. `:w!` overwrites an existing copy.
+    Write { file: Option<String>, force: bool },
+    /// `:wq` or `:x` saves and quits.
+    WriteQuit,
+    /// `:q` quits if there's nothing to save, `:q!` drops the unsaved changes.
+    Quit { force: bool },
+    /// `:e [file]` opens another file or reloads this one, `:e!` drops the unsaved changes.
+    Edit { file: Option<String>, force: bool },
+    /// `:export [profile]`
+    Export { profile: Option<String> },
+    /// `:goto <day>` selects the first activity of the day.
+    Goto(Date),
+    /// `:holiday add|remove <day>`
+    Holiday { add: bool, day: Date },
+    /// `:off add|remove <day> [kind]`, days off are vacation days unless a kind is given.
+    DayOff { add: bool, day: Date, kind: DayKind },
+    /// `:set <key>=<value>` changes a setting of the config.
+    Set { key: String, value: String },
+    /// `:help` shows the keys.
+    Help,
+}
+
+/// The commands and what they do, for the help.
+pub const COMMANDS: [(&str, &str); 10] = [
+    (":w [file]", "save, or write a copy to another file"),
+    (":wq, :x", "save and quit"),
+    (":q[!]", "quit, ! drops the unsaved changes"),
+    (":e[!] [file]", "open another file or reload this one"),
+    (":export [profile]", "export with a profile of the config"),
+    (":goto <day>", "select the first activity of a day"),
+    (":holiday add|remove <day>", "add or remove a holiday"),
+    (":off add|remove <day> [kind]", "add or remove a day off"),
+    (
+        ":set <setting>=<value>",
+        "change work_day_hours or free_holidays",
+    ),
+    (":help", "show the keys"),
+];
+
+impl FromStr for ExCommand {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, args) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let args = args.trim();
+        let (name, force) = match name.strip_suffix('!') {
+            Some(name) => (name, true),
+            None => (name, false),
+        };
+        let arg = (!args.is_empty()).then(|| args.to_string());
+        let no_args = |command| {
+            if args.is_empty() {
+                Ok(command)
+            } else {
+                Err("the command doesn't take arguments")
+            }
+        };
+        match name {
+            "w" | "write" => Ok(Self::Write { file: arg, force }),
+            "wq" | "x" => no_args(Self::WriteQuit),
+            "q" | "quit" => no_args(Self::Quit { force }),
+            "e" | "edit" => Ok(Self::Edit { file: arg, force }),
+            "export" => Ok(Self::Export { profile: arg }),
+            "goto" => Ok(Self::Goto(parse_day(args)?)),
+            "holiday" => {
+                let (add, day) = add_or_remove(args)?;
+                Ok(Self::Holiday {
+                    add,
+                    day: parse_day(day)?,
+                })
+            }
+            "off" => {
+                let (add, day) = add_or_remove(args)?;
+                // the kind is the last word, days can take more than one
+                let (day, kind) = match day
+                    .rsplit_once(char::is_whitespace)
+                    .and_then(|(d, k)| Some((d, k.parse::<DayKind>().ok()?)))
+                {
+                    Some((day, kind)) => (day, kind),
+                    None => (day, DayKind::Vacation),
+                };
+                if kind == DayKind::Holiday {
+                    return Err("holidays are added with :holiday");
+                }
+                Ok(Self::DayOff {
+                    add,
+                    day: parse_day(day.trim())?,
+                    kind,
+                })
+            }
+            "set" => {
+                let (key, value) = args
+                    .split_once('=')
+                    .or_else(|| args.split_once(char::is_whitespace))
+                    .ok_or("expected a setting and its value, like work_day_hours=7.5")?;
+                Ok(Self::Set {
+                    key: key.trim().to_string(),
+                    value: value.trim().to_string(),
+                })
+            }
+            "h" | "help" => no_args(Self::Help),
+            "" => Err("no command given"),
+            _ => Err("unknown command"),
+        }
+    }
+}
+
+/// Splits the `add` or `remove` at the start of the arguments from the rest.
+fn add_or_remove(args: &str) -> Result<(bool, &str), &'static str> {
+    let (verb, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    match verb {
+        "add" => Ok((true, rest.trim())),
+        "remove" | "rm" => Ok((false, rest.trim())),
+        _ => Err("expected add or remove"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use time::macros::date;
+
+    use super::*;
+
+    #[test]
+    fn files() {
+        assert_eq!(
+            "w".parse(),
+            Ok(ExCommand::Write {
+                file: None,
+                force: false
+            })
+        );
+        assert_eq!(
+            "w! other.csv".parse(),
+            Ok(ExCommand::Write {
+                file: Some("other.csv".into()),
+                force: true
+            })
+        );
+        assert_eq!(" q! ".parse(), Ok(ExCommand::Quit { force: true }));
+        assert_eq!("x".parse(), Ok(ExCommand::WriteQuit));
+        assert!("q now".parse::<ExCommand>().is_err());
+    }
+
+    #[test]
+    fn days() {
+        assert_eq!(
+            "goto 12/10/2022".parse(),
+            Ok(ExCommand::Goto(date!(2022 - 10 - 12)))
+        );
+        assert_eq!(
+            "holiday add 25/12/2022".parse(),
+            Ok(ExCommand::Holiday {
+                add: true,
+                day: date!(2022 - 12 - 25)
+            })
+        );
+        assert_eq!(
+            "off rm 2022-12-27 sick".parse(),
+            Ok(ExCommand::DayOff {
+                add: false,
+                day: date!(2022 - 12 - 27),
+                kind: DayKind::SickLeave
+            })
+        );
+        assert!("holiday 25/12/2022".parse::<ExCommand>().is_err());
+    }
+
+    #[test]
+    fn settings() {
+        let expected = Ok(ExCommand::Set {
+            key: "work_day_hours".into(),
+            value: "7.5".into(),
+        });
+        assert_eq!("set work_day_hours=7.5".parse(), expected);
+        assert_eq!("set work_day_hours 7.5".parse(), expected);
+        assert!("set".parse::<ExCommand>().is_err());
+    }
+}
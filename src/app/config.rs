@@ -1,23 +1,78 @@
 use std::{
     any::Any,
+    collections::BTreeMap,
     fs::File,
-    io::{self, BufReader, BufWriter, Write},
-    path::Path,
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
 };
 
-use crate::app::App;
+use super::allowance::VacationPolicy;
+use super::export::ExportProfile;
+use crate::combo_buffer::ComboAction;
 use crate::traits::EditingPopUp;
+use crate::util::{is_weekend, Zone};
 use serde::{Deserialize, Serialize};
+use time::Date;
 
 use tui::{
     style::{Color, Style},
     widgets::{Block, Borders, Paragraph},
 };
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, PartialOrd, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Config {
     pub work_day_hours: f32,
     pub free_holidays: bool,
+    #[serde(default)]
+    pub export_profiles: BTreeMap<String, ExportProfile>,
+    /// Overrides `work_day_hours` from the day each schedule takes effect.
+    #[serde(default)]
+    pub schedules: Vec<Schedule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vacation: Option<VacationPolicy>,
+    /// Where backups are kept, defaults to `effort/backups` in the user's data directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_dir: Option<PathBuf>,
+    /// How many backups of each file are kept, 0 disables them.
+    #[serde(default = "default_backups")]
+    pub backups: usize,
+    #[serde(default)]
+    pub save_policy: SavePolicy,
+    /// The home time zone, a name from the time zone database like `Europe/Paris` or a fixed
+    /// offset like `+01:00`. Activities are shown and added up on its wall clock, defaults to the
+    /// system's time zone. Activities recorded at home are stored without an offset, so they keep
+    /// their times when the home zone changes.
+    #[serde(default, skip_serializing_if = "Zone::is_system")]
+    pub home_zone: Zone,
+    /// Key sequences like `dd` or `<C-r>` and the actions they're bound to, replacing the default
+    /// bindings. Binding a sequence to `null` removes it.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub keymap: BTreeMap<String, Option<ComboAction>>,
+}
+
+/// When changes are written to the file.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SavePolicy {
+    /// As soon as something changes.
+    #[default]
+    OnChange,
+    /// Every this many seconds, if something changed.
+    Interval(u64),
+    /// Only when asked to.
+    Manual,
+}
+
+fn default_backups() -> usize {
+    10
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Schedule {
+    /// The first day this schedule applies to.
+    pub from: Date,
+    /// The hours expected to be worked on each day of the week, starting on monday.
+    pub hours: [f32; 7],
 }
 
 impl Default for Config {
@@ -25,32 +80,86 @@ impl Default for Config {
         Config {
             work_day_hours: 8.0,
             free_holidays: true,
+            export_profiles: BTreeMap::new(),
+            schedules: Vec::new(),
+            vacation: None,
+            backup_dir: None,
+            backups: default_backups(),
+            save_policy: SavePolicy::default(),
+            home_zone: Zone::system(),
+            keymap: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// The hours expected to be worked on `date` according to the schedule in effect on that day.
+    pub fn expected_hours(&self, date: Date) -> f32 {
+        let weekday = usize::from(date.weekday().number_days_from_monday());
+        match self
+            .schedules
+            .iter()
+            .filter(|s| s.from <= date)
+            .max_by_key(|s| s.from)
+        {
+            Some(schedule) => schedule.hours[weekday],
+            None if is_weekend(&date) => 0.0,
+            None => self.work_day_hours,
+        }
+    }
+
+    pub fn backup_dir(&self) -> PathBuf {
+        match &self.backup_dir {
+            Some(dir) => dir.clone(),
+            None => {
+                let mut dir = dirs::data_dir().unwrap_or_default();
+                dir.push("effort");
+                dir.push("backups");
+                dir
+            }
+        }
+    }
+
+    /// The export profile with the given name, `None` selects the `default` profile which falls
+    /// back to the builtin layout if it isn't configured.
+    pub fn export_profile(&self, name: Option<&str>) -> Option<ExportProfile> {
+        match name {
+            Some(name) => self.export_profiles.get(name).cloned(),
+            None => Some(
+                self.export_profiles
+                    .get("default")
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
         }
     }
 }
 
 pub fn load_config<P: AsRef<Path>>(path: P) -> io::Result<Config> {
     match File::open(path) {
-        Ok(f) => {
-            let file = BufReader::new(f);
-            Ok(serde_json::from_reader(file).unwrap_or_default())
+        Ok(mut f) => {
+            // dates can only be deserialized from borrowed strings
+            let mut s = String::new();
+            f.read_to_string(&mut s)?;
+            serde_json::from_str(&s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
         }
         Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
         Err(e) => Err(e),
     }
 }
 
-pub fn store_config<W>(writer: W, config: Config) -> io::Result<()>
+pub fn store_config<W>(writer: W, config: &Config) -> io::Result<()>
 where
     W: Write,
 {
-    let file = BufWriter::new(writer);
-    serde_json::to_writer_pretty(file, &config)?;
-    Ok(())
+    let mut file = BufWriter::new(writer);
+    serde_json::to_writer_pretty(&mut file, config)?;
+    file.flush()
 }
 
 #[derive(Debug, Clone)]
 pub struct ConfigBeingBuilt {
+    base: Config,
     pub work_day_hours: String,
     pub free_holidays: String,
     pub selected: ConfigSelected,
@@ -80,10 +189,22 @@ impl ConfigSelected {
 }
 
 impl ConfigBeingBuilt {
+    /// Set the field for the setting named like in the config file.
+    pub fn set(&mut self, key: &str, value: String) -> Result<(), &'static str> {
+        self.selected = match key {
+            "work_day_hours" => ConfigSelected::WorkDayHours,
+            "free_holidays" => ConfigSelected::FreeHolidays,
+            _ => return Err("unknown setting, expected work_day_hours or free_holidays"),
+        };
+        *self.selected_buf() = value;
+        Ok(())
+    }
+
     pub fn new(config: Config) -> Self {
         Self {
             work_day_hours: config.work_day_hours.to_string(),
             free_holidays: config.free_holidays.to_string(),
+            base: config,
             selected: ConfigSelected::WorkDayHours,
             editing: true,
         }
@@ -114,13 +235,6 @@ impl EditingPopUp for ConfigBeingBuilt {
         self.editing
     }
 
-    fn submit(&self, app: &mut App) -> Result<(), &'static str> {
-        app.config = self.try_into()?;
-        app.pop_up = None;
-        let _ = app.save_to(&app.filename);
-        Ok(())
-    }
-
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -180,6 +294,7 @@ impl TryFrom<&ConfigBeingBuilt> for Config {
         Ok(Config {
             work_day_hours,
             free_holidays,
+            ..builder.base.clone()
         })
     }
 }
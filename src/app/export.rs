@@ -0,0 +1,241 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+};
+
+use serde::{Deserialize, Serialize};
+use time::{format_description, Date, Duration, Time};
+
+use super::activity::Activity;
+use crate::util::fmt_duration;
+
+/// A layout for [`App::export`](super::App::export).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ExportProfile {
+    /// The columns to write, in order.
+    pub columns: Vec<ExportColumn>,
+    /// Whether to write a row with the column headers first.
+    pub header_row: bool,
+    pub separator: char,
+    /// A [`time`] format description used for the `{day}` placeholder.
+    pub date_format: String,
+    /// A [`time`] format description used for the `{start}` and `{end}` placeholders.
+    pub time_format: String,
+    /// Where to write the export to, `-` means stdout and `{file}` is replaced with the name of
+    /// the activities file. Defaults to `{file}-export.csv`.
+    pub output: Option<String>,
+}
+
+/// A column of an export, the template can contain the placeholders `{day}`, `{action}`,
+/// `{issue}`, `{start}`, `{end}`, `{duration}` (as `HH:MM`) and `{hours}` (as decimal hours).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ExportColumn {
+    #[serde(default)]
+    pub header: String,
+    pub template: String,
+}
+
+impl ExportColumn {
+    fn new(header: &str, template: &str) -> Self {
+        Self {
+            header: header.into(),
+            template: template.into(),
+        }
+    }
+}
+
+impl Default for ExportProfile {
+    fn default() -> Self {
+        Self {
+            columns: vec![
+                ExportColumn::new("Day", "{day}"),
+                ExportColumn::new("Action", "{action}"),
+                ExportColumn::new("Start", "{start}"),
+                ExportColumn::new("End", "{end}"),
+                ExportColumn::new("Duration", "{duration}"),
+            ],
+            header_row: false,
+            separator: ',',
+            date_format: "[month]-[day]-[year]".into(),
+            time_format: "[hour repr:12]:[minute] [period]".into(),
+            output: None,
+        }
+    }
+}
+
+fn invalid_input<E: ToString>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, e.to_string())
+}
+
+impl ExportProfile {
+    /// The path this profile writes to, or `None` if it writes to stdout.
+    pub fn output_path(&self, filename: &str) -> Option<String> {
+        match self.output.as_deref() {
+            Some("-") => None,
+            Some(output) => Some(output.replace("{file}", filename)),
+            None => Some(format!("{}-export.csv", filename)),
+        }
+    }
+
+    /// Write the activities between `from` and `to` (inclusive) following this profile. Activities
+    /// that cross midnight are written as one row for each day.
+    pub fn write<'a, I>(
+        &self,
+        filename: &str,
+        activities: I,
+        from: Option<Date>,
+        to: Option<Date>,
+    ) -> io::Result<()>
+    where
+        I: Iterator<Item = &'a Activity>,
+    {
+        let mut rows = activities
+            // the part after midnight can be on `from`
+            .filter(|a| from.is_none_or(|from| a.day >= from.previous_day().unwrap_or(from)))
+            .filter(|a| to.is_none_or(|to| a.day <= to))
+            .map(|a| {
+                split_at_midnight(a).ok_or_else(|| {
+                    io::Error::other(format!("activity {:?} doesn't have an end time", a))
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .filter(|r| from.is_none_or(|from| r.day >= from))
+            .filter(|r| to.is_none_or(|to| r.day <= to))
+            .collect::<Vec<_>>();
+        rows.sort_unstable_by_key(|r| (r.day, r.start, r.activity));
+        if !self.separator.is_ascii() {
+            return Err(invalid_input("the separator must be an ascii character"));
+        }
+        let date_fmt = format_description::parse(&self.date_format).map_err(invalid_input)?;
+        let time_fmt = format_description::parse(&self.time_format).map_err(invalid_input)?;
+        let output: Box<dyn Write> = match self.output_path(filename) {
+            Some(path) => Box::new(File::create(path)?),
+            None => Box::new(io::stdout()),
+        };
+        let mut w = csv::WriterBuilder::new()
+            .delimiter(self.separator as u8)
+            .from_writer(output);
+        if self.header_row {
+            w.write_record(self.columns.iter().map(|c| &c.header))?;
+        }
+        for row in rows {
+            let a = row.activity;
+            let field = |name: &str| -> io::Result<String> {
+                Ok(match name {
+                    "day" => row.day.format(&date_fmt).map_err(invalid_input)?,
+                    "action" => a.action.clone(),
+                    "issue" => a.issue.clone(),
+                    "start" => row.start.format(&time_fmt).map_err(invalid_input)?,
+                    "end" => row.end.format(&time_fmt).map_err(invalid_input)?,
+                    "duration" => fmt_duration(row.duration),
+                    "hours" => format!("{:.2}", row.duration.whole_minutes() as f64 / 60.0),
+                    _ => return Err(invalid_input(format!("unknown placeholder {{{}}}", name))),
+                })
+            };
+            let record = self
+                .columns
+                .iter()
+                .map(|c| render_template(&c.template, field))
+                .collect::<io::Result<Vec<_>>>()?;
+            w.write_record(record)?;
+        }
+        w.flush()
+    }
+}
+
+/// The part of an activity done on one day.
+struct Row<'a> {
+    activity: &'a Activity,
+    day: Date,
+    start: Time,
+    end: Time,
+    duration: Duration,
+}
+
+/// The parts of a finished activity on each day it was done, `None` if it didn't finish.
+fn split_at_midnight(a: &Activity) -> Option<Vec<Row<'_>>> {
+    let end = a.end()?;
+    if !a.crosses_midnight() {
+        return Some(vec![Row {
+            activity: a,
+            day: a.day,
+            start: a.start_time,
+            end: end.time(),
+            duration: end - a.start(),
+        }]);
+    }
+    let midnight = end.date().midnight();
+    Some(vec![
+        Row {
+            activity: a,
+            day: a.day,
+            start: a.start_time,
+            end: Time::MIDNIGHT,
+            duration: midnight - a.start(),
+        },
+        Row {
+            activity: a,
+            day: end.date(),
+            start: Time::MIDNIGHT,
+            end: end.time(),
+            duration: end - midnight,
+        },
+    ])
+}
+
+fn render_template<F>(template: &str, field: F) -> io::Result<String>
+where
+    F: Fn(&str) -> io::Result<String>,
+{
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| invalid_input(format!("unclosed placeholder in '{}'", template)))?;
+        rendered.push_str(&field(&rest[start + 1..start + end])?);
+        rest = &rest[start + end + 1..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn field(name: &str) -> io::Result<String> {
+        match name {
+            "issue" => Ok("EF-1".into()),
+            "action" => Ok("coding".into()),
+            _ => Err(invalid_input(name)),
+        }
+    }
+
+    #[test]
+    fn plain_text() {
+        assert_eq!(render_template("text", field).unwrap(), "text");
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(
+            render_template("{issue}: {action}!", field).unwrap(),
+            "EF-1: coding!"
+        );
+    }
+
+    #[test]
+    fn unknown_placeholder() {
+        assert!(render_template("{nope}", field).is_err());
+    }
+
+    #[test]
+    fn unclosed_placeholder() {
+        assert!(render_template("{issue", field).is_err());
+    }
+}
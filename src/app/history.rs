@@ -1,29 +1,127 @@
-use std::mem::swap;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    mem::swap,
+    path::Path,
+};
 
-use super::{activity::Activity, state::State};
+use serde::{Deserialize, Serialize};
+use time::{Date, PrimitiveDateTime};
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+use super::{
+    activity::Activity,
+    calendar::{Calendar, DayKind},
+    state::State,
+};
+use crate::util::{self, time_fmt::DATE_FMT};
+
+/// How many actions are kept, the oldest ones are forgotten first.
+const MAX_ENTRIES: usize = 200;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
 pub enum Action {
     DeleteActivity(Activity),
-    Edit { prev: Activity },
+    Edit {
+        prev: Activity,
+    },
     AddActivity(Activity),
+    AddDate(Date, DayKind),
+    RemoveDate(Date, DayKind),
+    ReplaceDate {
+        prev: (Date, DayKind),
+        new: (Date, DayKind),
+    },
+}
+
+impl Action {
+    pub fn describe(&self) -> String {
+        let activity = |a: &Activity| {
+            format!(
+                "'{}' ({}) on {}",
+                a.action,
+                a.issue,
+                a.day.format(DATE_FMT).unwrap()
+            )
+        };
+        let date =
+            |(d, kind): &(Date, DayKind)| format!("{} {}", kind, d.format(DATE_FMT).unwrap());
+        match self {
+            Self::DeleteActivity(a) => format!("deleted {}", activity(a)),
+            Self::Edit { prev } => format!("edited {}", activity(prev)),
+            Self::AddActivity(a) => format!("added {}", activity(a)),
+            Self::AddDate(d, kind) => format!("added {}", date(&(*d, *kind))),
+            Self::RemoveDate(d, kind) => format!("removed {}", date(&(*d, *kind))),
+            Self::ReplaceDate { prev, new } => {
+                format!("changed {} to {}", date(prev), date(new))
+            }
+        }
+    }
+}
+
+/// An action and when it was done.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub at: PrimitiveDateTime,
+    pub action: Action,
+    /// Whether it's undone and redone together with the entry before it, like the deletes of
+    /// `3dd`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub joined: bool,
 }
 
-#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
 pub struct History {
-    past: Vec<Action>,
-    future: Vec<Action>,
+    past: Vec<Entry>,
+    future: Vec<Entry>,
+    /// How many actions were recorded since the group was started, `None` outside of one.
+    #[serde(skip)]
+    group: Option<usize>,
 }
 
 impl History {
     pub fn frwd(&mut self, a: Action) {
-        self.past.push(a);
+        self.past.push(Entry {
+            at: util::now(),
+            action: a,
+            joined: self.group.is_some_and(|n| n > 0),
+        });
+        if let Some(n) = &mut self.group {
+            *n += 1;
+        }
+        if self.past.len() > MAX_ENTRIES {
+            self.past.remove(0);
+        }
         self.future.clear();
     }
 
-    pub fn redo(&mut self, state: &mut State) {
-        if let Some(mut action) = self.future.pop() {
-            match &mut action {
+    /// The actions recorded until [`History::end_group`] is called are undone and redone at once.
+    pub fn start_group(&mut self) {
+        self.group = Some(0);
+    }
+
+    pub fn end_group(&mut self) {
+        self.group = None;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.past.is_empty() && self.future.is_empty()
+    }
+
+    /// The actions that can be undone, most recent first.
+    pub fn past(&self) -> impl Iterator<Item = &Entry> {
+        self.past.iter().rev()
+    }
+
+    pub fn redo(&mut self, state: &mut State, calendar: &mut Calendar) {
+        self.redo_one(state, calendar);
+        while self.future.last().is_some_and(|e| e.joined) {
+            self.redo_one(state, calendar);
+        }
+    }
+
+    fn redo_one(&mut self, state: &mut State, calendar: &mut Calendar) {
+        if let Some(mut entry) = self.future.pop() {
+            match &mut entry.action {
                 Action::DeleteActivity(a) => {
                     state.remove_by_id(a.day, a.id);
                 }
@@ -36,14 +134,29 @@ impl History {
                 Action::AddActivity(a) => {
                     state.add(a.clone());
                 }
+                Action::AddDate(date, kind) => {
+                    calendar.insert(*date, *kind);
+                }
+                Action::RemoveDate(date, _) => {
+                    calendar.remove(*date);
+                }
+                Action::ReplaceDate { prev, new } => {
+                    calendar.remove(prev.0);
+                    calendar.insert(new.0, new.1);
+                }
             }
-            self.past.push(action);
+            self.past.push(entry);
         }
     }
 
-    pub fn undo(&mut self, state: &mut State) {
-        if let Some(mut action) = self.past.pop() {
-            match &mut action {
+    pub fn undo(&mut self, state: &mut State, calendar: &mut Calendar) {
+        while self.undo_one(state, calendar) {}
+    }
+
+    /// Returns whether the entry undone was joined to the one before it.
+    fn undo_one(&mut self, state: &mut State, calendar: &mut Calendar) -> bool {
+        if let Some(mut entry) = self.past.pop() {
+            match &mut entry.action {
                 Action::DeleteActivity(a) => {
                     state.add(a.clone());
                 }
@@ -56,8 +169,100 @@ impl History {
                 Action::AddActivity(act) => {
                     state.remove_by_id(act.day, act.id);
                 }
+                Action::AddDate(date, _) => {
+                    calendar.remove(*date);
+                }
+                Action::RemoveDate(date, kind) => {
+                    calendar.insert(*date, *kind);
+                }
+                Action::ReplaceDate { prev, new } => {
+                    calendar.remove(new.0);
+                    calendar.insert(prev.0, prev.1);
+                }
+            }
+            let joined = entry.joined;
+            self.future.push(entry);
+            joined
+        } else {
+            false
+        }
+    }
+}
+
+pub fn load_history<P: AsRef<Path>>(path: P) -> io::Result<History> {
+    match File::open(format!("{}-history", path.as_ref().display())) {
+        Ok(mut f) => {
+            let mut s = String::new();
+            f.read_to_string(&mut s)?;
+            serde_json::from_str(&s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(History::default()),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn store_history<W>(writer: W, history: &History) -> io::Result<()>
+where
+    W: Write,
+{
+    let mut file = BufWriter::new(writer);
+    serde_json::to_writer(&mut file, history)?;
+    file.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use time::macros::{date, datetime};
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut history = History::default();
+        let a = Activity::start_at(datetime!(2022-01-03 9:00), "coding".into(), "EF-1".into());
+        history.frwd(Action::AddActivity(a));
+        history.frwd(Action::AddDate(date!(2022 - 01 - 04), DayKind::SickLeave));
+        let mut out = Vec::new();
+        store_history(&mut out, &history).unwrap();
+        let loaded: History = serde_json::from_slice(&out).unwrap();
+        assert_eq!(loaded, history);
+    }
+
+    #[test]
+    fn bounded() {
+        let mut history = History::default();
+        for _ in 0..MAX_ENTRIES + 10 {
+            history.frwd(Action::AddDate(date!(2022 - 01 - 04), DayKind::Vacation));
+        }
+        assert_eq!(history.past().count(), MAX_ENTRIES);
+    }
+
+    #[test]
+    fn groups_are_undone_at_once() {
+        let mut history = History::default();
+        let mut state: State = std::iter::empty().collect();
+        let mut calendar = Calendar::default();
+        let days = [
+            date!(2022 - 01 - 03),
+            date!(2022 - 01 - 04),
+            date!(2022 - 01 - 05),
+        ];
+        for (i, day) in days.into_iter().enumerate() {
+            if i == 1 {
+                history.start_group();
             }
-            self.future.push(action);
+            calendar.insert(day, DayKind::Vacation);
+            history.frwd(Action::AddDate(day, DayKind::Vacation));
         }
+        history.end_group();
+        history.undo(&mut state, &mut calendar);
+        assert_eq!(calendar.get(days[0]), Some(DayKind::Vacation));
+        assert_eq!(calendar.get(days[1]), None);
+        assert_eq!(calendar.get(days[2]), None);
+        history.redo(&mut state, &mut calendar);
+        assert_eq!(calendar.get(days[2]), Some(DayKind::Vacation));
+        history.undo(&mut state, &mut calendar);
+        history.undo(&mut state, &mut calendar);
+        assert!(calendar.all().next().is_none());
     }
 }
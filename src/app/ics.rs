@@ -0,0 +1,235 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    iter::successors,
+    path::Path,
+};
+
+use time::{macros::format_description, Date};
+
+/// The dates covered by the events of an ics calendar.
+///
+/// Multi-day events contribute every day from `DTSTART` up to (but excluding) `DTEND`, and
+/// yearly recurring events are expanded up to `until`.
+pub fn load_ics_dates<P: AsRef<Path>>(path: P, until: Date) -> io::Result<Vec<Date>> {
+    let mut s = String::new();
+    File::open(path)?.read_to_string(&mut s)?;
+    parse_ics_dates(&s, until).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[derive(Debug, Default)]
+struct Event {
+    start: Option<Date>,
+    end: Option<Date>,
+    rrule: Option<String>,
+    exdates: Vec<Date>,
+}
+
+pub fn parse_ics_dates(s: &str, until: Date) -> Result<Vec<Date>, String> {
+    let mut dates = Vec::new();
+    let mut event: Option<Event> = None;
+    for line in unfold(s) {
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name.split(';').next().unwrap_or_default(), value),
+            None => continue,
+        };
+        match (name, &mut event) {
+            ("BEGIN", None) if value == "VEVENT" => event = Some(Event::default()),
+            ("END", Some(_)) if value == "VEVENT" => {
+                let e = event.take().unwrap();
+                expand_event(&e, until, &mut dates)?;
+            }
+            ("DTSTART", Some(e)) => e.start = Some(parse_ics_date(value)?),
+            ("DTEND", Some(e)) => e.end = Some(parse_ics_end(value)?),
+            ("RRULE", Some(e)) => e.rrule = Some(value.to_string()),
+            ("EXDATE", Some(e)) => {
+                for d in value.split(',') {
+                    e.exdates.push(parse_ics_date(d)?);
+                }
+            }
+            _ => {}
+        }
+    }
+    dates.sort_unstable();
+    dates.dedup();
+    Ok(dates)
+}
+
+/// Joins the lines that were folded by prefixing them with whitespace.
+fn unfold(s: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in s.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.trim_end().to_string()),
+        }
+    }
+    lines
+}
+
+fn parse_ics_date(value: &str) -> Result<Date, String> {
+    let date = value
+        .get(..8)
+        .ok_or_else(|| format!("invalid date '{}'", value))?;
+    Date::parse(date, format_description!("[year][month][day]"))
+        .map_err(|e| format!("invalid date '{}': {}", value, e))
+}
+
+/// The first day not covered by an event ending at `value`.
+fn parse_ics_end(value: &str) -> Result<Date, String> {
+    let date = parse_ics_date(value)?;
+    match value.get(8..) {
+        // a date-time end includes the day it ends on, unless it ends at midnight
+        Some(time) if !time.is_empty() && !time.starts_with("T000000") => date
+            .next_day()
+            .ok_or_else(|| format!("date out of range '{}'", value)),
+        _ => Ok(date),
+    }
+}
+
+fn expand_event(e: &Event, until: Date, dates: &mut Vec<Date>) -> Result<(), String> {
+    let start = match e.start {
+        Some(start) => start,
+        None => return Err("event without a DTSTART".into()),
+    };
+    let n_days = e
+        .end
+        .map(|end| (end - start).whole_days().max(1))
+        .unwrap_or(1);
+    let occurrences = match &e.rrule {
+        Some(rrule) => yearly_occurrences(start, rrule, until)?,
+        None => vec![start],
+    };
+    for occurrence in occurrences {
+        let days = successors(Some(occurrence), |d| d.next_day()).take(n_days as usize);
+        dates.extend(days.filter(|d| !e.exdates.contains(d)));
+    }
+    Ok(())
+}
+
+fn yearly_occurrences(start: Date, rrule: &str, until: Date) -> Result<Vec<Date>, String> {
+    let mut count = None;
+    let mut interval = 1;
+    let mut until = until;
+    for part in rrule.split(';') {
+        match part.split_once('=') {
+            Some(("FREQ", "YEARLY")) => {}
+            Some(("FREQ", freq)) => {
+                return Err(format!("unsupported recurrence frequency {}", freq))
+            }
+            Some(("COUNT", n)) => {
+                count = Some(n.parse().map_err(|_| format!("invalid COUNT '{}'", n))?)
+            }
+            Some(("INTERVAL", n)) => {
+                interval = n
+                    .parse()
+                    .ok()
+                    .filter(|i| *i > 0)
+                    .ok_or_else(|| format!("invalid INTERVAL '{}'", n))?
+            }
+            Some(("UNTIL", d)) => until = until.min(parse_ics_date(d)?),
+            _ => {}
+        }
+    }
+    let occurrences = (0..)
+        .step_by(interval)
+        .map(|n: i32| start.year() + n)
+        .take_while(|year| *year <= until.year())
+        // skip the years where the date doesn't exist, like the 29th of February
+        .filter_map(|year| Date::from_calendar_date(year, start.month(), start.day()).ok())
+        .take_while(|d| *d <= until);
+    Ok(match count {
+        Some(count) => occurrences.take(count).collect(),
+        None => occurrences.collect(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use time::macros::date;
+
+    use super::*;
+
+    fn calendar(events: &str) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{}END:VCALENDAR\r\n",
+            events
+        )
+    }
+
+    #[test]
+    fn single_day() {
+        let ics = calendar(
+            "BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20221225\r\nDTEND;VALUE=DATE:20221226\r\nSUMMARY:Christmas\r\nEND:VEVENT\r\n",
+        );
+        assert_eq!(
+            parse_ics_dates(&ics, date!(2022 - 12 - 31)).unwrap(),
+            vec![date!(2022 - 12 - 25)]
+        );
+    }
+
+    #[test]
+    fn multi_day() {
+        let ics = calendar(
+            "BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20220801\r\nDTEND;VALUE=DATE:20220804\r\nEND:VEVENT\r\n",
+        );
+        assert_eq!(
+            parse_ics_dates(&ics, date!(2022 - 12 - 31)).unwrap(),
+            vec![
+                date!(2022 - 08 - 01),
+                date!(2022 - 08 - 02),
+                date!(2022 - 08 - 03)
+            ]
+        );
+    }
+
+    #[test]
+    fn date_time_end() {
+        let ics = calendar(
+            "BEGIN:VEVENT\r\nDTSTART:20220801T090000Z\r\nDTEND:20220802T120000Z\r\nEND:VEVENT\r\n",
+        );
+        assert_eq!(
+            parse_ics_dates(&ics, date!(2022 - 12 - 31)).unwrap(),
+            vec![date!(2022 - 08 - 01), date!(2022 - 08 - 02)]
+        );
+    }
+
+    #[test]
+    fn yearly() {
+        let ics = calendar(
+            "BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20200101\r\nRRULE:FREQ=YEARLY\r\nEXDATE;VALUE=DATE:20210101\r\nEND:VEVENT\r\n",
+        );
+        assert_eq!(
+            parse_ics_dates(&ics, date!(2022 - 12 - 31)).unwrap(),
+            vec![date!(2020 - 01 - 01), date!(2022 - 01 - 01)]
+        );
+    }
+
+    #[test]
+    fn yearly_with_count_and_interval() {
+        let ics = calendar(
+            "BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20200229\r\nRRULE:FREQ=YEARLY;INTERVAL=2;COUNT=2\r\nEND:VEVENT\r\n",
+        );
+        assert_eq!(
+            parse_ics_dates(&ics, date!(2030 - 12 - 31)).unwrap(),
+            vec![date!(2020 - 02 - 29), date!(2024 - 02 - 29)]
+        );
+    }
+
+    #[test]
+    fn folded_lines() {
+        let ics = calendar("BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:2022\r\n 1225\r\nEND:VEVENT\r\n");
+        assert_eq!(
+            parse_ics_dates(&ics, date!(2022 - 12 - 31)).unwrap(),
+            vec![date!(2022 - 12 - 25)]
+        );
+    }
+
+    #[test]
+    fn unsupported_frequency() {
+        let ics = calendar(
+            "BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20220101\r\nRRULE:FREQ=MONTHLY\r\nEND:VEVENT\r\n",
+        );
+        assert!(parse_ics_dates(&ics, date!(2022 - 12 - 31)).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use std::{cmp::Ordering, collections::BTreeMap};
+
+use time::{Date, Duration, PrimitiveDateTime};
+
+use super::activity::Activity;
+
+/// How much was worked on an issue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssueReport {
+    pub issue: String,
+    pub total: Duration,
+    pub sessions: usize,
+    pub first_day: Date,
+    pub last_day: Date,
+}
+
+/// The columns the issue breakdown can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IssueColumn {
+    Issue,
+    Total,
+    Sessions,
+    FirstDay,
+    LastDay,
+}
+
+impl IssueColumn {
+    pub const ALL: [IssueColumn; 5] = [
+        IssueColumn::Issue,
+        IssueColumn::Total,
+        IssueColumn::Sessions,
+        IssueColumn::FirstDay,
+        IssueColumn::LastDay,
+    ];
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Issue => Self::Total,
+            Self::Total => Self::Sessions,
+            Self::Sessions => Self::FirstDay,
+            Self::FirstDay => Self::LastDay,
+            Self::LastDay => Self::Issue,
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Issue => "Issue",
+            Self::Total => "Total",
+            Self::Sessions => "Sessions",
+            Self::FirstDay => "First day",
+            Self::LastDay => "Last day",
+        }
+    }
+
+    fn cmp(self, a: &IssueReport, b: &IssueReport) -> Ordering {
+        match self {
+            Self::Issue => a.issue.cmp(&b.issue),
+            Self::Total => a.total.cmp(&b.total),
+            Self::Sessions => a.sessions.cmp(&b.sessions),
+            Self::FirstDay => a.first_day.cmp(&b.first_day),
+            Self::LastDay => a.last_day.cmp(&b.last_day),
+        }
+    }
+}
+
+/// Every issue worked on, sorted by `column`. Ties are broken by the name of the issue.
+pub fn issue_breakdown<'a, I>(
+    activities: I,
+    now: PrimitiveDateTime,
+    column: IssueColumn,
+    descending: bool,
+) -> Vec<IssueReport>
+where
+    I: Iterator<Item = &'a Activity>,
+{
+    let mut issues = BTreeMap::<&str, IssueReport>::new();
+    for a in activities {
+        let spent = a.time_spent(now).unwrap_or_default();
+        issues
+            .entry(&a.issue)
+            .and_modify(|r| {
+                r.total += spent;
+                r.sessions += 1;
+                r.first_day = r.first_day.min(a.day);
+                r.last_day = r.last_day.max(a.day);
+            })
+            .or_insert_with(|| IssueReport {
+                issue: a.issue.clone(),
+                total: spent,
+                sessions: 1,
+                first_day: a.day,
+                last_day: a.day,
+            });
+    }
+    let mut issues = issues.into_values().collect::<Vec<_>>();
+    issues.sort_by(|a, b| {
+        let ord = if descending {
+            column.cmp(b, a)
+        } else {
+            column.cmp(a, b)
+        };
+        ord.then_with(|| a.issue.cmp(&b.issue))
+    });
+    issues
+}
+
+#[cfg(test)]
+mod test {
+    use time::macros::{date, datetime, time};
+
+    use super::*;
+
+    fn activities() -> Vec<Activity> {
+        let mut a = Activity::start_at(datetime!(2022-01-03 9:00), "coding".into(), "EF-1".into());
+        a.end_time = Some(time!(11:00));
+        let mut b = Activity::start_at(datetime!(2022-01-05 13:00), "review".into(), "EF-1".into());
+        b.end_time = Some(time!(14:30));
+        let mut c = Activity::start_at(datetime!(2022-01-04 23:00), "deploy".into(), "EF-2".into());
+        c.end_time = Some(time!(1:00));
+        let d = Activity::start_at(datetime!(2022-01-06 9:00), "coding".into(), "EF-3".into());
+        vec![a, b, c, d]
+    }
+
+    fn order(column: IssueColumn, descending: bool) -> Vec<String> {
+        issue_breakdown(
+            activities().iter(),
+            datetime!(2022-01-06 9:45),
+            column,
+            descending,
+        )
+        .into_iter()
+        .map(|r| r.issue)
+        .collect()
+    }
+
+    #[test]
+    fn reports() {
+        let reports = issue_breakdown(
+            activities().iter(),
+            datetime!(2022-01-06 9:45),
+            IssueColumn::Issue,
+            false,
+        );
+        assert_eq!(
+            reports,
+            [
+                IssueReport {
+                    issue: "EF-1".into(),
+                    total: Duration::minutes(210),
+                    sessions: 2,
+                    first_day: date!(2022 - 01 - 03),
+                    last_day: date!(2022 - 01 - 05),
+                },
+                IssueReport {
+                    issue: "EF-2".into(),
+                    total: Duration::hours(2),
+                    sessions: 1,
+                    first_day: date!(2022 - 01 - 04),
+                    last_day: date!(2022 - 01 - 04),
+                },
+                IssueReport {
+                    issue: "EF-3".into(),
+                    total: Duration::minutes(45),
+                    sessions: 1,
+                    first_day: date!(2022 - 01 - 06),
+                    last_day: date!(2022 - 01 - 06),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn sorted_by_issue() {
+        assert_eq!(order(IssueColumn::Issue, false), ["EF-1", "EF-2", "EF-3"]);
+        assert_eq!(order(IssueColumn::Issue, true), ["EF-3", "EF-2", "EF-1"]);
+    }
+
+    #[test]
+    fn sorted_by_total() {
+        assert_eq!(order(IssueColumn::Total, false), ["EF-3", "EF-2", "EF-1"]);
+        assert_eq!(order(IssueColumn::Total, true), ["EF-1", "EF-2", "EF-3"]);
+    }
+
+    #[test]
+    fn sorted_by_sessions_ties_by_issue() {
+        assert_eq!(
+            order(IssueColumn::Sessions, false),
+            ["EF-2", "EF-3", "EF-1"]
+        );
+        assert_eq!(order(IssueColumn::Sessions, true), ["EF-1", "EF-2", "EF-3"]);
+    }
+
+    #[test]
+    fn sorted_by_first_day() {
+        assert_eq!(
+            order(IssueColumn::FirstDay, false),
+            ["EF-1", "EF-2", "EF-3"]
+        );
+        assert_eq!(order(IssueColumn::FirstDay, true), ["EF-3", "EF-2", "EF-1"]);
+    }
+
+    #[test]
+    fn sorted_by_last_day() {
+        assert_eq!(order(IssueColumn::LastDay, false), ["EF-2", "EF-1", "EF-3"]);
+        assert_eq!(order(IssueColumn::LastDay, true), ["EF-3", "EF-1", "EF-2"]);
+    }
+}
@@ -0,0 +1,71 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    process,
+};
+
+/// An advisory lock on an activities file, held by creating `<file>.lock` with the pid of the
+/// process holding it. The lock is released when dropped.
+#[derive(Debug)]
+pub struct Lock {
+    path: PathBuf,
+}
+
+impl Lock {
+    pub fn acquire(filename: &str) -> io::Result<Self> {
+        let path = PathBuf::from(format!("{}.lock", filename));
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut f) => {
+                    writeln!(f, "{}", process::id())?;
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    let pid = fs::read_to_string(&path)?;
+                    let pid = pid.trim();
+                    if is_running(pid) {
+                        return Err(io::Error::new(
+                            io::ErrorKind::AlreadyExists,
+                            format!(
+                                "{} is open in another effort process (pid {}), delete {} if it isn't",
+                                filename,
+                                pid,
+                                path.display()
+                            ),
+                        ));
+                    }
+                    // left behind by a process that crashed
+                    fs::remove_file(&path)?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Whether the process with the given pid is still running. When that can't be known it's assumed
+/// to be.
+#[cfg(unix)]
+fn is_running(pid: &str) -> bool {
+    match pid.parse::<libc::pid_t>() {
+        // signal 0 isn't sent, it only checks that the process exists
+        Ok(pid) if pid > 0 => {
+            let exists = unsafe { libc::kill(pid, 0) } == 0;
+            // it exists but belongs to someone else
+            exists || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+        }
+        _ => true,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: &str) -> bool {
+    true
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
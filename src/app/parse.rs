@@ -0,0 +1,387 @@
+use time::{Date, Duration, Month, Time, Weekday};
+
+use crate::util;
+
+/// Parse a time typed by the user, relative to `now`.
+///
+/// Besides `HH:MM` it understands `now` (or nothing at all), `last` (the `last` time given),
+/// offsets from now like `-15m`, `+1h30` or `15 min ago`, 12 hour times like `9am` or `5:30pm`
+/// and times without a colon like `1730`. When a `start` is given, durations like `2h` or `45m`
+/// are taken as the time that long after it. A lone hour is only accepted when it's the current
+/// one, and means now.
+pub fn parse_time(
+    s: &str,
+    now: Time,
+    last: Option<Time>,
+    start: Option<Time>,
+) -> Result<Time, &'static str> {
+    let now = Time::from_hms(now.hour(), now.minute(), 0).unwrap();
+    let s = s.trim().to_ascii_lowercase();
+    if s.is_empty() || s == "now" {
+        return Ok(now);
+    } else if s == "last" {
+        return last.ok_or("no previous time available");
+    }
+    if let Some(offset) = s.strip_prefix('+') {
+        let offset = parse_duration(offset).ok_or("failed to parse time: invalid offset")?;
+        return offset_time(now, offset);
+    }
+    if let Some(offset) = s.strip_prefix('-') {
+        let offset = parse_duration(offset).ok_or("failed to parse time: invalid offset")?;
+        return offset_time(now, -offset);
+    }
+    if let Some(offset) = s.strip_suffix("ago") {
+        let offset =
+            parse_duration(offset.trim_end()).ok_or("failed to parse time: invalid offset")?;
+        return offset_time(now, -offset);
+    }
+    if let Some(start) = start {
+        if let Some(d) = parse_duration(&s) {
+            // the end can be on the next day, but not any later
+            if d >= Duration::DAY {
+                return Err("failed to parse time: lasts a day or more");
+            }
+            return Ok(start + d);
+        }
+    }
+    if let Some(t) = s.strip_suffix("am") {
+        return parse_12_hour(t.trim_end(), 0);
+    }
+    if let Some(t) = s.strip_suffix("pm") {
+        return parse_12_hour(t.trim_end(), 12);
+    }
+    if (3..=4).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_digit()) {
+        let (hour, minute) = s.split_at(s.len() - 2);
+        return hour_minute(hour, minute);
+    }
+
+    let (hour, minute) = s.split_once(':').unwrap_or((&s, ""));
+    if minute.is_empty() {
+        let hour = parse_hour(hour)?;
+        if hour == now.hour() {
+            Ok(now)
+        } else {
+            Err("can't use current minute because you are not inputing current hour")
+        }
+    } else {
+        hour_minute(hour, minute)
+    }
+}
+
+fn parse_hour(hour: &str) -> Result<u8, &'static str> {
+    hour.trim()
+        .parse()
+        .map_err(|_| "failed to parse time: invalid hour")
+}
+
+fn hour_minute(hour: &str, minute: &str) -> Result<Time, &'static str> {
+    let hour = parse_hour(hour)?;
+    let minute = minute
+        .trim()
+        .parse()
+        .map_err(|_| "failed to parse time: invalid minute")?;
+    Time::from_hms(hour, minute, 0)
+        .map_err(|_| "failed to parse time: hour or minute out of bounds")
+}
+
+/// Parse `h`, `h:mm` in a 12 hour clock, `offset` is 12 for pm times.
+fn parse_12_hour(s: &str, offset: u8) -> Result<Time, &'static str> {
+    let (hour, minute) = s.split_once(':').unwrap_or((s, "0"));
+    match parse_hour(hour)? {
+        hour @ 1..=12 => hour_minute(&((hour % 12) + offset).to_string(), minute),
+        _ => Err("failed to parse time: hour out of bounds"),
+    }
+}
+
+/// `now` moved by `offset`, which has to keep it on the same day.
+fn offset_time(now: Time, offset: Duration) -> Result<Time, &'static str> {
+    let minutes = i64::from(now.hour()) * 60 + i64::from(now.minute()) + offset.whole_minutes();
+    if !(0..24 * 60).contains(&minutes) {
+        return Err("failed to parse time: the offset moves it to another day");
+    }
+    Ok(Time::from_hms((minutes / 60) as u8, (minutes % 60) as u8, 0).unwrap())
+}
+
+/// Parse a duration like `2h`, `45m`, `1h30`, `1h 30min` or `15 min`.
+fn parse_duration(s: &str) -> Option<Duration> {
+    let (n, rest) = leading_number(s.trim())?;
+    let (unit, rest) = leading_unit(rest);
+    if is_hours(unit) {
+        if rest.is_empty() {
+            return hours(n);
+        }
+        let (m, rest) = leading_number(rest)?;
+        let (unit, rest) = leading_unit(rest);
+        if rest.is_empty() && (unit.is_empty() || is_minutes(unit)) {
+            hours(n)?.checked_add(minutes(m)?)
+        } else {
+            None
+        }
+    } else if is_minutes(unit) && rest.is_empty() {
+        minutes(n)
+    } else {
+        None
+    }
+}
+
+// `Duration::hours` and `Duration::minutes` overflow on large numbers
+fn hours(n: i64) -> Option<Duration> {
+    n.checked_mul(3600).map(Duration::seconds)
+}
+
+fn minutes(n: i64) -> Option<Duration> {
+    n.checked_mul(60).map(Duration::seconds)
+}
+
+fn leading_number(s: &str) -> Option<(i64, &str)> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let n = s[..end].parse().ok()?;
+    Some((n, s[end..].trim_start()))
+}
+
+fn leading_unit(s: &str) -> (&str, &str) {
+    let end = s
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(s.len());
+    (&s[..end], s[end..].trim_start())
+}
+
+fn is_hours(unit: &str) -> bool {
+    matches!(unit, "h" | "hr" | "hrs" | "hour" | "hours")
+}
+
+fn is_minutes(unit: &str) -> bool {
+    matches!(unit, "m" | "min" | "mins" | "minute" | "minutes")
+}
+
+const OUT_OF_RANGE: &str = "failed to parse date: date out of range";
+
+/// Parse a day typed by the user, see [`parse_day_at`].
+pub fn parse_day(s: &str) -> Result<Date, &'static str> {
+    parse_day_at(s, util::now().date())
+}
+
+/// Parse a day typed by the user, relative to `today`.
+///
+/// Besides `day[/month[/year]]`, where what's left out is taken from today, it understands
+/// `today` (or nothing at all), `yesterday`, `tomorrow`, weekday names (`mon` is the most recent
+/// monday, `last mon` the one before today), days from today like `-2` or `+1` and ISO dates like
+/// `2026-10-17`.
+pub fn parse_day_at(s: &str, today: Date) -> Result<Date, &'static str> {
+    let s = s.trim().to_ascii_lowercase();
+    match s.as_str() {
+        "" | "today" => return Ok(today),
+        "yesterday" => return today.previous_day().ok_or(OUT_OF_RANGE),
+        "tomorrow" => return today.next_day().ok_or(OUT_OF_RANGE),
+        _ => {}
+    }
+    if let Some(name) = s.strip_prefix("last ") {
+        let weekday = parse_weekday(name.trim())?;
+        return most_recent(weekday, today.previous_day().ok_or(OUT_OF_RANGE)?);
+    }
+    if let Ok(weekday) = parse_weekday(&s) {
+        return most_recent(weekday, today);
+    }
+    if let Some(days) = s.strip_prefix('-') {
+        return today.checked_sub(parse_days(days)?).ok_or(OUT_OF_RANGE);
+    }
+    if let Some(days) = s.strip_prefix('+') {
+        return today.checked_add(parse_days(days)?).ok_or(OUT_OF_RANGE);
+    }
+
+    let mut iter = s.split(&['/', '-'][..]).map(str::trim);
+    let first = iter.next().unwrap_or_default();
+    let (year, month, day) = if first.len() == 4 {
+        // ISO, year first
+        let year = parse_year(first)?;
+        let month = parse_month(iter.next().ok_or("failed to parse date: missing month")?)?;
+        let day = parse_day_of_month(iter.next().ok_or("failed to parse date: missing day")?)?;
+        (year, month, day)
+    } else {
+        let day = parse_day_of_month(first)?;
+        let month = iter.next().map(parse_month).transpose()?;
+        let year = iter.next().map(parse_year).transpose()?;
+        (
+            year.unwrap_or(today.year()),
+            month.unwrap_or(today.month()),
+            day,
+        )
+    };
+    if iter.next().is_some() {
+        return Err("failed to parse date: too many parts");
+    }
+    Date::from_calendar_date(year, month, day)
+        .map_err(|_| "failed to parse date: day out of bounds for the month")
+}
+
+fn parse_day_of_month(day: &str) -> Result<u8, &'static str> {
+    day.parse().map_err(|_| "failed to parse date: invalid day")
+}
+
+fn parse_month(month: &str) -> Result<Month, &'static str> {
+    let month: u8 = month
+        .parse()
+        .map_err(|_| "failed to parse date: invalid month number")?;
+    Month::try_from(month).map_err(|_| "failed to parse date: month number out of bounds")
+}
+
+fn parse_year(year: &str) -> Result<i32, &'static str> {
+    year.parse()
+        .map_err(|_| "failed to parse date: invalid year")
+}
+
+fn parse_days(days: &str) -> Result<Duration, &'static str> {
+    let days: i64 = days
+        .trim()
+        .parse()
+        .map_err(|_| "failed to parse date: invalid number of days")?;
+    // `Duration::days` overflows on large numbers
+    days.checked_mul(86_400)
+        .map(Duration::seconds)
+        .ok_or(OUT_OF_RANGE)
+}
+
+/// Parse a weekday from its name or a prefix of it at least 3 letters long.
+fn parse_weekday(name: &str) -> Result<Weekday, &'static str> {
+    const WEEKDAYS: [(&str, Weekday); 7] = [
+        ("monday", Weekday::Monday),
+        ("tuesday", Weekday::Tuesday),
+        ("wednesday", Weekday::Wednesday),
+        ("thursday", Weekday::Thursday),
+        ("friday", Weekday::Friday),
+        ("saturday", Weekday::Saturday),
+        ("sunday", Weekday::Sunday),
+    ];
+    WEEKDAYS
+        .iter()
+        .find(|(full, _)| name.len() >= 3 && full.starts_with(name))
+        .map(|(_, weekday)| *weekday)
+        .ok_or("failed to parse date: unknown weekday")
+}
+
+/// The most recent day that's a `weekday`, `from` included.
+fn most_recent(weekday: Weekday, from: Date) -> Result<Date, &'static str> {
+    let mut day = from;
+    while day.weekday() != weekday {
+        day = day.previous_day().ok_or(OUT_OF_RANGE)?;
+    }
+    Ok(day)
+}
+
+#[cfg(test)]
+mod test {
+    use time::macros::{date, time};
+
+    use super::*;
+
+    const NOW: Time = time!(14:27:45);
+
+    fn parse(s: &str) -> Result<Time, &'static str> {
+        parse_time(s, NOW, Some(time!(12:00)), None)
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(parse("9:05"), Ok(time!(9:05)));
+        assert_eq!(parse("14"), Ok(time!(14:27)));
+        assert!(parse("9").is_err());
+        assert_eq!(parse(""), Ok(time!(14:27)));
+        assert_eq!(parse("NOW"), Ok(time!(14:27)));
+        assert_eq!(parse("last"), Ok(time!(12:00)));
+    }
+
+    #[test]
+    fn relative() {
+        assert_eq!(parse("-15m"), Ok(time!(14:12)));
+        assert_eq!(parse("+1h30"), Ok(time!(15:57)));
+        assert_eq!(parse("15 min ago"), Ok(time!(14:12)));
+        assert_eq!(parse("2 hours ago"), Ok(time!(12:27)));
+        assert!(parse("-15").is_err());
+    }
+
+    #[test]
+    fn relative_stays_on_the_day() {
+        let at = |s, now| parse_time(s, now, None, None);
+        assert_eq!(at("-5m", time!(0:05)), Ok(time!(0:00)));
+        assert!(at("-15m", time!(0:05)).is_err());
+        assert!(at("+1h", time!(23:30)).is_err());
+        assert!(at("+9999999999999999h", NOW).is_err());
+        assert!(at("-1h 9999999999999999m", NOW).is_err());
+        let start = Some(time!(23:00));
+        assert_eq!(parse_time("2h", NOW, None, start), Ok(time!(1:00)));
+        assert!(parse_time("24h", NOW, None, start).is_err());
+    }
+
+    #[test]
+    fn twelve_hour() {
+        assert_eq!(parse("9am"), Ok(time!(9:00)));
+        assert_eq!(parse("5:30pm"), Ok(time!(17:30)));
+        assert_eq!(parse("12am"), Ok(time!(0:00)));
+        assert_eq!(parse("12 pm"), Ok(time!(12:00)));
+        assert!(parse("13pm").is_err());
+    }
+
+    #[test]
+    fn without_colon() {
+        assert_eq!(parse("1730"), Ok(time!(17:30)));
+        assert_eq!(parse("930"), Ok(time!(9:30)));
+        assert!(parse("1790").is_err());
+    }
+
+    #[test]
+    fn duration_from_start() {
+        let start = Some(time!(9:00));
+        assert_eq!(parse_time("2h", NOW, None, start), Ok(time!(11:00)));
+        assert_eq!(parse_time("45m", NOW, None, start), Ok(time!(9:45)));
+        assert_eq!(parse_time("1h 30min", NOW, None, start), Ok(time!(10:30)));
+        assert!(parse("2h").is_err());
+    }
+
+    // a saturday
+    const TODAY: Date = date!(2026 - 10 - 17);
+
+    fn day(s: &str) -> Result<Date, &'static str> {
+        parse_day_at(s, TODAY)
+    }
+
+    #[test]
+    fn plain_days() {
+        assert_eq!(day(""), Ok(TODAY));
+        assert_eq!(day("3"), Ok(date!(2026 - 10 - 03)));
+        assert_eq!(day("31/1"), Ok(date!(2026 - 01 - 31)));
+        assert_eq!(day("3-2-2025"), Ok(date!(2025 - 02 - 03)));
+        assert_eq!(
+            day("31/2"),
+            Err("failed to parse date: day out of bounds for the month")
+        );
+        assert_eq!(
+            day("3/13"),
+            Err("failed to parse date: month number out of bounds")
+        );
+    }
+
+    #[test]
+    fn named_days() {
+        assert_eq!(day("Yesterday"), Ok(date!(2026 - 10 - 16)));
+        assert_eq!(day("today"), Ok(TODAY));
+        assert_eq!(day("mon"), Ok(date!(2026 - 10 - 12)));
+        assert_eq!(day("saturday"), Ok(TODAY));
+        assert_eq!(day("last sat"), Ok(date!(2026 - 10 - 10)));
+        assert_eq!(day("last friday"), Ok(date!(2026 - 10 - 16)));
+        assert_eq!(day("last fr"), Err("failed to parse date: unknown weekday"));
+    }
+
+    #[test]
+    fn relative_days() {
+        assert_eq!(day("-2"), Ok(date!(2026 - 10 - 15)));
+        assert_eq!(day("+1"), Ok(date!(2026 - 10 - 18)));
+        assert_eq!(day("+9999999"), Err(OUT_OF_RANGE));
+        assert_eq!(day("-999999999999999999"), Err(OUT_OF_RANGE));
+    }
+
+    #[test]
+    fn iso() {
+        assert_eq!(day("2026-10-17"), Ok(TODAY));
+        assert_eq!(day("2026-10"), Err("failed to parse date: missing day"));
+    }
+}
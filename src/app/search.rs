@@ -0,0 +1,77 @@
+use regex::{Regex, RegexBuilder};
+
+use super::activity::Activity;
+
+/// A search over the action and issue of the activities.
+///
+/// The query is a regex, if it isn't a valid one it's matched as plain text. Like in vim, the
+/// search is case insensitive unless the query has an uppercase letter.
+#[derive(Debug, Clone)]
+pub struct Search {
+    query: String,
+    regex: Regex,
+}
+
+impl Search {
+    pub fn new(query: String) -> Self {
+        let case_insensitive = !query.chars().any(char::is_uppercase);
+        let regex = RegexBuilder::new(&query)
+            .case_insensitive(case_insensitive)
+            .build()
+            .or_else(|_| {
+                RegexBuilder::new(&regex::escape(&query))
+                    .case_insensitive(case_insensitive)
+                    .build()
+            })
+            .expect("an escaped regex is always valid");
+        Self { query, regex }
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn matches(&self, a: &Activity) -> bool {
+        self.regex.is_match(&a.action) || self.regex.is_match(&a.issue)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use time::macros::datetime;
+
+    use super::*;
+
+    fn activity(action: &str, issue: &str) -> Activity {
+        Activity::start_at(datetime!(2022-01-03 9:00), action.into(), issue.into())
+    }
+
+    #[test]
+    fn substring() {
+        let search = Search::new("view".into());
+        assert!(search.matches(&activity("code review", "")));
+        assert!(!search.matches(&activity("coding", "")));
+    }
+
+    #[test]
+    fn issue() {
+        assert!(Search::new("ef-12".into()).matches(&activity("", "EF-12")));
+    }
+
+    #[test]
+    fn smart_case() {
+        assert!(!Search::new("Review".into()).matches(&activity("review", "")));
+    }
+
+    #[test]
+    fn regex() {
+        let search = Search::new("^EF-1[0-9]$".into());
+        assert!(search.matches(&activity("", "EF-12")));
+        assert!(!search.matches(&activity("", "EF-123")));
+    }
+
+    #[test]
+    fn invalid_regex_is_literal() {
+        assert!(Search::new("fix (".into()).matches(&activity("fix (again)", "")));
+    }
+}
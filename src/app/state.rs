@@ -62,6 +62,16 @@ impl Deref for State {
     }
 }
 
+impl FromIterator<Activity> for State {
+    fn from_iter<I: IntoIterator<Item = Activity>>(iter: I) -> Self {
+        let mut state = Self(BTreeMap::new());
+        for a in iter {
+            state.0.entry(Reverse(a.day)).or_default().add(a);
+        }
+        state
+    }
+}
+
 impl From<BTreeMap<Reverse<Date>, ActivityVec>> for State {
     fn from(m: BTreeMap<Reverse<Date>, ActivityVec>) -> Self {
         Self(m)
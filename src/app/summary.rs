@@ -0,0 +1,83 @@
+use std::iter::successors;
+
+use time::{Duration, PrimitiveDateTime};
+
+use super::{
+    calendar::{Calendar, DateList, DayKind},
+    config::Config,
+    state::State,
+};
+
+/// How much was worked compared to how much was expected to be worked.
+///
+/// Only the days from the first activity or day off up to the last one are considered, days off
+/// in the future don't count yet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub worked: Duration,
+    pub expected: Duration,
+    /// The days in the period where some work was expected, before taking days off into account.
+    pub work_days: u16,
+    /// How many of the work days were taken off.
+    pub day_off_credit: f32,
+}
+
+impl Summary {
+    pub fn new(
+        activities: &State,
+        calendar: &Calendar,
+        config: &Config,
+        now: PrimitiveDateTime,
+    ) -> Self {
+        let today = now.date();
+        let days_off = || {
+            calendar
+                .iter(DateList::DaysOff)
+                .map(|(d, _)| *d)
+                .filter(move |d| *d <= today)
+        };
+        let first = activities.keys().map(|d| d.0).chain(days_off()).min();
+        let last = activities.keys().map(|d| d.0).chain(days_off()).max();
+        let mut summary = Self {
+            worked: activities
+                .values()
+                .flat_map(|acts| acts.iter())
+                .filter_map(|a| a.time_spent(now))
+                .sum(),
+            expected: Duration::ZERO,
+            work_days: 0,
+            day_off_credit: 0.0,
+        };
+        let (first, last) = match first.zip(last) {
+            Some(period) => period,
+            None => return summary,
+        };
+        for day in successors(Some(first), |d| d.next_day()).take_while(|d| *d <= last) {
+            let hours = config.expected_hours(day);
+            let kind = calendar.get(day);
+            if hours <= 0.0 || (config.free_holidays && kind == Some(DayKind::Holiday)) {
+                continue;
+            }
+            let credit = kind
+                .filter(|k| *k != DayKind::Holiday)
+                .map(DayKind::credit)
+                .unwrap_or(0.0);
+            summary.work_days = summary
+                .work_days
+                .checked_add(1)
+                .expect("that's too many days bro");
+            summary.day_off_credit += credit;
+            summary.expected += Duration::seconds_f32(hours * 3600.0 * (1.0 - credit));
+        }
+        summary
+    }
+
+    pub fn overtime(&self) -> Duration {
+        self.worked - self.expected
+    }
+
+    /// The work days that weren't taken off.
+    pub fn days_worked(&self) -> f32 {
+        (f32::from(self.work_days) - self.day_off_credit).max(0.0)
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet},
+    fs,
+    hash::{Hash, Hasher},
+    io,
+};
+
+use time::Date;
+
+use super::{activity::ActivityId, Activity, DayKind, Ledger};
+
+/// The suffixes of the files that hold the data of an activities file. The history isn't one of
+/// them, it only matters to the session that wrote it.
+const FILES: [&str; 4] = ["", "-off", "-holidays", "-balance"];
+
+/// A hash of the contents of the data files of an activities file, to notice when they are
+/// changed by something else.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Fingerprint(Vec<Option<u64>>);
+
+impl Fingerprint {
+    pub fn read(filename: &str) -> io::Result<Self> {
+        FILES
+            .iter()
+            .map(|suffix| match fs::read(format!("{}{}", filename, suffix)) {
+                Ok(contents) => {
+                    let mut hasher = DefaultHasher::new();
+                    contents.hash(&mut hasher);
+                    Ok(Some(hasher.finish()))
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e),
+            })
+            .collect::<io::Result<_>>()
+            .map(Self)
+    }
+}
+
+/// What the data files hold.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Contents {
+    pub activities: BTreeMap<ActivityId, Activity>,
+    pub calendar: BTreeMap<Date, DayKind>,
+    pub ledger: Ledger,
+}
+
+impl Contents {
+    /// Merge the changes made to `self` by this session, `ours`, with the ones made by someone
+    /// else, `theirs`.
+    pub fn merge(&self, ours: &Self, theirs: &Self) -> Self {
+        Self {
+            activities: merge(&self.activities, &ours.activities, &theirs.activities),
+            calendar: merge(&self.calendar, &ours.calendar, &theirs.calendar),
+            ledger: if ours.ledger == self.ledger {
+                theirs.ledger.clone()
+            } else {
+                ours.ledger.clone()
+            },
+        }
+    }
+}
+
+/// The data files as they were when they were last loaded or saved.
+#[derive(Debug, Default, Clone)]
+pub struct Snapshot {
+    pub fingerprint: Fingerprint,
+    pub contents: Contents,
+}
+
+/// Merge the changes made to `base` by two sides. Entries one side didn't touch take the value
+/// the other side gave them, entries both sides changed keep the value in `ours`.
+fn merge<K, V>(
+    base: &BTreeMap<K, V>,
+    ours: &BTreeMap<K, V>,
+    theirs: &BTreeMap<K, V>,
+) -> BTreeMap<K, V>
+where
+    K: Ord + Clone,
+    V: PartialEq + Clone,
+{
+    ours.keys()
+        .chain(theirs.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|k| {
+            let ours = ours.get(k);
+            let v = if ours == base.get(k) {
+                theirs.get(k)
+            } else {
+                ours
+            };
+            v.map(|v| (k.clone(), v.clone()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn map(entries: &[(u32, &'static str)]) -> BTreeMap<u32, &'static str> {
+        entries.iter().copied().collect()
+    }
+
+    #[test]
+    fn both_sides_add() {
+        let base = map(&[(1, "a")]);
+        let ours = map(&[(1, "a"), (2, "b")]);
+        let theirs = map(&[(1, "a"), (3, "c")]);
+        assert_eq!(
+            merge(&base, &ours, &theirs),
+            map(&[(1, "a"), (2, "b"), (3, "c")])
+        );
+    }
+
+    #[test]
+    fn untouched_takes_their_change() {
+        let base = map(&[(1, "a"), (2, "b")]);
+        let ours = map(&[(1, "x"), (2, "b")]);
+        let theirs = map(&[(1, "a"), (2, "y")]);
+        assert_eq!(merge(&base, &ours, &theirs), map(&[(1, "x"), (2, "y")]));
+    }
+
+    #[test]
+    fn deletions() {
+        let base = map(&[(1, "a"), (2, "b")]);
+        let ours = map(&[(2, "b")]);
+        let theirs = map(&[(1, "a")]);
+        assert_eq!(merge(&base, &ours, &theirs), map(&[]));
+    }
+
+    #[test]
+    fn conflict_keeps_ours() {
+        let base = map(&[(1, "a")]);
+        let ours = map(&[(1, "x")]);
+        let theirs = map(&[]);
+        assert_eq!(merge(&base, &ours, &theirs), map(&[(1, "x")]));
+    }
+}
@@ -0,0 +1,353 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use time::{Date, Duration, Month};
+
+use crate::{
+    app::{load_ics_dates, parse_day, ActivityBeingBuilt, App, DayKind},
+    util::{
+        fmt_balance, fmt_days, fmt_duration,
+        time_fmt::{DATE_FMT_FULL, TIME_FMT},
+    },
+};
+
+#[derive(Debug, Parser)]
+#[command(about = "Track the effort you put into your work")]
+pub struct Args {
+    /// The file where activities are stored
+    pub file: String,
+    /// Export the activities using the default profile, same as the `export` command
+    #[arg(short, long)]
+    pub export: bool,
+    /// Open the file without changing or locking it
+    #[arg(long)]
+    pub read_only: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start a timer, stopping the one currently running
+    Start { issue: String, action: String },
+    /// Stop the running timer
+    Stop,
+    /// Add a finished activity
+    Add {
+        issue: String,
+        action: String,
+        /// Defaults to today
+        #[arg(long, default_value = "")]
+        day: String,
+        /// Defaults to now
+        #[arg(long, default_value = "")]
+        start: String,
+        /// Leave empty to keep the activity open
+        #[arg(long, default_value = "")]
+        end: String,
+    },
+    /// List the activities
+    List {
+        #[arg(long, value_parser = parse_day_arg)]
+        from: Option<Date>,
+        #[arg(long, value_parser = parse_day_arg)]
+        to: Option<Date>,
+    },
+    /// Show the running timer and how much was done today
+    Status,
+    /// Export the activities following one of the profiles in the config
+    Export {
+        /// The name of the profile, defaults to the `default` profile
+        #[arg(long)]
+        profile: Option<String>,
+        #[arg(long, value_parser = parse_day_arg)]
+        from: Option<Date>,
+        #[arg(long, value_parser = parse_day_arg)]
+        to: Option<Date>,
+    },
+    /// Import the dates of the events in an ics calendar as holidays or days off
+    ImportIcs {
+        ics: PathBuf,
+        /// The kind of day to import the dates as: holiday, vacation, sick, unpaid, half or comp
+        #[arg(long = "as", default_value = "holiday")]
+        kind: DayKind,
+        #[arg(long, value_parser = parse_day_arg)]
+        from: Option<Date>,
+        /// Recurring events are expanded up to this day, defaults to the end of the current year
+        #[arg(long, value_parser = parse_day_arg)]
+        to: Option<Date>,
+    },
+    /// Show how many vacation days were used, are planned and remain this year
+    Vacation,
+    /// List or restore the backups of the file
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommand,
+    },
+    /// Show the overtime balance, including what was carried over from previous files
+    Balance {
+        #[command(subcommand)]
+        command: Option<BalanceCommand>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum BackupCommand {
+    /// List the backups, most recent first
+    List,
+    /// Replace the file with one of its backups, the current contents are backed up first
+    Restore {
+        /// The name of the backup, as shown by `backup list`
+        name: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum BalanceCommand {
+    /// Record a manual change to the balance, like a payout or a correction
+    Adjust {
+        #[arg(allow_negative_numbers = true)]
+        hours: f32,
+        #[arg(long, default_value = "")]
+        note: String,
+        /// Defaults to today
+        #[arg(long, value_parser = parse_day_arg)]
+        day: Option<Date>,
+    },
+    /// Carry over the closing balance of the activities file of the previous period
+    Chain { previous: String },
+    /// Start with the given balance instead of carrying it over, leave empty to clear it
+    Open {
+        #[arg(allow_negative_numbers = true)]
+        hours: Option<f32>,
+    },
+}
+
+impl Command {
+    /// Whether the command changes the file, the others don't need to lock it.
+    pub fn writes(&self) -> bool {
+        match self {
+            Self::Start { .. } | Self::Stop | Self::Add { .. } | Self::ImportIcs { .. } => true,
+            Self::Backup { command } => matches!(command, BackupCommand::Restore { .. }),
+            Self::Balance { command } => command.is_some(),
+            Self::List { .. } | Self::Status | Self::Export { .. } | Self::Vacation => false,
+        }
+    }
+}
+
+fn parse_day_arg(s: &str) -> Result<Date, &'static str> {
+    parse_day(s)
+}
+
+pub fn run(app: &mut App, command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::Start { issue, action } => {
+            app.start_timer_on(action, issue)
+                .map_err(anyhow::Error::msg)?;
+            save(app)?;
+        }
+        Command::Stop => {
+            app.stop_timer().map_err(anyhow::Error::msg)?;
+            save(app)?;
+        }
+        Command::Add {
+            issue,
+            action,
+            day,
+            start,
+            end,
+        } => {
+            let mut builder = ActivityBeingBuilt::new(None, &app.config.home_zone);
+            builder.issue = issue;
+            builder.action = action;
+            builder.day = day;
+            builder.start_time = start;
+            builder.end_time = end;
+            app.submit_activity(&builder).map_err(anyhow::Error::msg)?;
+            save(app)?;
+        }
+        Command::List { from, to } => list(app, from, to),
+        Command::Status => status(app),
+        Command::Export { profile, from, to } => {
+            let profile = app
+                .config
+                .export_profile(profile.as_deref())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("no export profile named '{}'", profile.unwrap_or_default())
+                })?;
+            app.export(&profile, from, to)?;
+            if let Some(path) = profile.output_path(app.filename()) {
+                println!("exported to {}", path);
+            }
+        }
+        Command::ImportIcs {
+            ics,
+            kind,
+            from,
+            to,
+        } => {
+            let to = to.unwrap_or_else(|| {
+                Date::from_calendar_date(app.config.home_zone.now().year(), Month::December, 31)
+                    .unwrap()
+            });
+            let mut imported = 0;
+            app.start_repeat();
+            for date in load_ics_dates(ics, to)?
+                .into_iter()
+                .filter(|d| from.is_none_or(|from| *d >= from) && *d <= to)
+            {
+                let added = match kind {
+                    DayKind::Holiday => app.add_holiday(date),
+                    kind => app.add_day_off(date, kind),
+                };
+                match added {
+                    Ok(()) => imported += 1,
+                    Err(e) => println!("skipped {}: {}", date.format(DATE_FMT_FULL).unwrap(), e),
+                }
+            }
+            app.end_repeat();
+            println!("imported {} dates", imported);
+            save(app)?;
+        }
+        Command::Vacation => vacation(app)?,
+        Command::Backup {
+            command: BackupCommand::List,
+        } => {
+            for backup in app.backups()? {
+                println!(
+                    "{}\t{} {}",
+                    backup.name,
+                    backup.taken.format(DATE_FMT_FULL).unwrap(),
+                    backup.taken.format(TIME_FMT).unwrap()
+                );
+            }
+        }
+        Command::Backup {
+            command: BackupCommand::Restore { name },
+        } => {
+            app.restore_backup(&name)?;
+            println!("restored {}", name);
+        }
+        Command::Balance { command } => {
+            match command {
+                Some(BalanceCommand::Adjust { hours, note, day }) => app
+                    .adjust_balance(
+                        day.unwrap_or_else(|| app.config.home_zone.now().date()),
+                        hours,
+                        note,
+                    )
+                    .map_err(anyhow::Error::msg)?,
+                Some(BalanceCommand::Chain { previous }) => app.chain_balance(previous)?,
+                Some(BalanceCommand::Open { hours }) => {
+                    app.set_opening_balance(hours).map_err(anyhow::Error::msg)?
+                }
+                None => {}
+            }
+            save(app)?;
+            balance(app)?;
+        }
+    }
+    Ok(())
+}
+
+/// Save what the command changed, unless the save policy already did. Changes made to the file
+/// since it was loaded, by an open session of the app for example, are merged.
+fn save(app: &mut App) -> std::io::Result<()> {
+    if !app.is_dirty() {
+        return Ok(());
+    }
+    match app.save() {
+        Err(_) if app.changed_externally() => app.merge_external_changes(),
+        res => res,
+    }
+}
+
+fn list(app: &App, from: Option<Date>, to: Option<Date>) {
+    let now = app.config.home_zone.now();
+    let days = app
+        .activities()
+        .rev()
+        .filter(|(d, _)| from.is_none_or(|from| **d >= from))
+        .filter(|(d, _)| to.is_none_or(|to| **d <= to));
+    for (date, acts) in days {
+        for a in acts {
+            let end = match a.end_time {
+                Some(t) if a.crosses_midnight() => format!("{}+1", t.format(TIME_FMT).unwrap()),
+                Some(t) => t.format(TIME_FMT).unwrap(),
+                None if a.is_running(now) => "running".to_string(),
+                None => "None".to_string(),
+            };
+            println!(
+                "{}\t{}-{}\t{}\t{}\t{}\t{}",
+                date.format(DATE_FMT_FULL).unwrap(),
+                a.start_time.format(TIME_FMT).unwrap(),
+                end,
+                a.time_spent(now).map(fmt_duration).unwrap_or_default(),
+                a.issue,
+                a.action,
+                a.id,
+            );
+        }
+    }
+}
+
+fn vacation(app: &App) -> anyhow::Result<()> {
+    let allowance = app
+        .allowance()?
+        .ok_or_else(|| anyhow::anyhow!("no vacation policy in the config"))?;
+    println!("year\t{}", allowance.year);
+    println!("accrued\t{}", fmt_days(allowance.accrued));
+    match allowance.expires {
+        Some(expires) => println!(
+            "carried over\t{}\texpires on {}",
+            fmt_days(allowance.carried_over),
+            expires.format(DATE_FMT_FULL).unwrap()
+        ),
+        None => println!("carried over\t{}", fmt_days(allowance.carried_over)),
+    }
+    println!("expired\t{}", fmt_days(allowance.expired));
+    println!("used\t{}", fmt_days(allowance.used));
+    println!("planned\t{}", fmt_days(allowance.planned));
+    println!("remaining\t{}", fmt_days(allowance.remaining()));
+    Ok(())
+}
+
+fn balance(app: &App) -> anyhow::Result<()> {
+    let balance = app.balance(&app.summary())?;
+    let ledger = app.ledger();
+    match (ledger.opening, &ledger.previous) {
+        (None, Some(previous)) => println!(
+            "opening\t{}\tcarried over from {}",
+            fmt_balance(balance.opening),
+            previous
+        ),
+        _ => println!("opening\t{}", fmt_balance(balance.opening)),
+    }
+    println!("overtime\t{}", fmt_balance(balance.overtime));
+    for a in &ledger.adjustments {
+        println!(
+            "{}\t{}\t{}",
+            a.day.format(DATE_FMT_FULL).unwrap(),
+            fmt_balance(Duration::seconds_f32(a.hours * 3600.0)),
+            a.note
+        );
+    }
+    println!("closing\t{}", fmt_balance(balance.closing()));
+    Ok(())
+}
+
+fn status(app: &App) {
+    let now = app.config.home_zone.now();
+    match app.running_timer() {
+        Some(a) => println!(
+            "running: {} {} (since {}, {})",
+            a.issue,
+            a.action,
+            a.start_time.format(TIME_FMT).unwrap(),
+            a.time_spent(now).map(fmt_duration).unwrap_or_default(),
+        ),
+        None => println!("no timer running"),
+    }
+    let (today, _) = app.time_spent_on(now.date(), now);
+    println!("today: {}", fmt_duration(today));
+}
@@ -1,11 +1,143 @@
-use std::iter::Peekable;
+use std::{collections::BTreeMap, fmt};
 
-use crossterm::event::KeyCode;
-use lazy_static::lazy_static;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+/// A key with the modifiers held while pressing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Hash)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl From<KeyCode> for Key {
+    fn from(code: KeyCode) -> Self {
+        Self {
+            code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        let mut modifiers = event.modifiers;
+        // shift is already in the character
+        if let KeyCode::Char(_) = event.code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self {
+            code: event.code,
+            modifiers,
+        }
+    }
+}
+
+const KEY_NAMES: [(&str, KeyCode); 16] = [
+    ("Esc", KeyCode::Esc),
+    ("Enter", KeyCode::Enter),
+    ("CR", KeyCode::Enter),
+    ("Tab", KeyCode::Tab),
+    ("BS", KeyCode::Backspace),
+    ("Space", KeyCode::Char(' ')),
+    ("lt", KeyCode::Char('<')),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Del", KeyCode::Delete),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+];
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self.code {
+            KeyCode::F(n) => format!("F{}", n),
+            code => match KEY_NAMES.iter().find(|(_, c)| *c == code) {
+                Some((name, _)) => name.to_string(),
+                None => match code {
+                    KeyCode::Char(c) if self.modifiers.is_empty() => return write!(f, "{}", c),
+                    KeyCode::Char(c) => c.to_string(),
+                    _ => format!("{:?}", code),
+                },
+            },
+        };
+        write!(f, "<")?;
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "C-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "A-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "S-")?;
+        }
+        write!(f, "{}>", name)
+    }
+}
+
+/// Parses a key sequence written like in vim: `dd`, `G`, `<C-r>`, `<Esc>` or `<A-Enter>`.
+pub fn parse_keys(s: &str) -> Result<Vec<Key>, String> {
+    let mut keys = vec![];
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        let special = rest
+            .strip_prefix('<')
+            .and_then(|r| r.split_once('>'))
+            .filter(|(name, _)| !name.is_empty());
+        match special {
+            Some((name, r)) => {
+                keys.push(parse_special(name).ok_or_else(|| format!("unknown key <{}>", name))?);
+                rest = r;
+            }
+            None => {
+                keys.push(Key::from(KeyCode::Char(c)));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    if keys.is_empty() {
+        return Err("empty key sequence".to_string());
+    }
+    Ok(keys)
+}
+
+fn parse_special(name: &str) -> Option<Key> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = name;
+    while let Some((modifier, rest)) = name.split_once('-').filter(|(_, r)| !r.is_empty()) {
+        modifiers |= match modifier.to_ascii_uppercase().as_str() {
+            "C" => KeyModifiers::CONTROL,
+            "A" | "M" => KeyModifiers::ALT,
+            "S" => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+        name = rest;
+    }
+    let code = match KEY_NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+        Some((_, code)) => *code,
+        None => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                (Some('F' | 'f'), Some(_)) => KeyCode::F(name[1..].parse().ok()?),
+                _ => return None,
+            }
+        }
+    };
+    Some(Key { code, modifiers })
+}
+
+fn fmt_keys(keys: &[Key]) -> String {
+    keys.iter().map(Key::to_string).collect()
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
 struct ComboNode {
-    key: KeyCode,
+    key: Key,
     next: ActionOrMore,
 }
 
@@ -15,98 +147,278 @@ enum ActionOrMore {
     Combo(ComboAction),
 }
 
-fn build_combos<const N: usize>(sequences: [(&str, ComboAction); N]) -> Vec<ComboNode> {
-    fn build<I: Iterator<Item = char>>(
-        mut chars: Peekable<I>,
+/// Builds the tree the keys are looked up in. Sequences that start with another one can't be told
+/// apart from it, they're an error.
+fn build_combos<I>(sequences: I) -> Result<Vec<ComboNode>, String>
+where
+    I: IntoIterator<Item = (Vec<Key>, ComboAction)>,
+{
+    /// Any sequence in the tree, to tell which one a new one conflicts with.
+    fn any_sequence(tree: &[ComboNode]) -> Vec<Key> {
+        let mut keys = vec![];
+        let mut tree = tree;
+        while let Some(node) = tree.first() {
+            keys.push(node.key);
+            match &node.next {
+                ActionOrMore::Combo(_) => break,
+                ActionOrMore::More(next) => tree = next,
+            }
+        }
+        keys
+    }
+
+    fn build(
+        sequence: &[Key],
+        depth: usize,
         tree: &mut Vec<ComboNode>,
         action: ComboAction,
-    ) {
-        if let Some(key) = chars.next().map(KeyCode::Char) {
-            match tree.iter_mut().find(|n| n.key == key) {
-                Some(node) => match &mut node.next {
-                    ActionOrMore::Combo(_) => panic!("ambiguous combo defined"),
-                    ActionOrMore::More(next) => build(chars, next, action),
-                },
-                None => {
-                    let next = if chars.peek().is_some() {
-                        let mut more = vec![];
-                        build(chars, &mut more, action);
-                        ActionOrMore::More(more)
-                    } else {
-                        ActionOrMore::Combo(action)
-                    };
-                    tree.push(ComboNode { key, next });
+    ) -> Result<(), String> {
+        let key = sequence[depth];
+        let last = depth + 1 == sequence.len();
+        match tree.iter_mut().find(|n| n.key == key) {
+            Some(node) => match &mut node.next {
+                ActionOrMore::Combo(_) if last => {
+                    Err(format!("{} is bound twice", fmt_keys(sequence)))
                 }
+                ActionOrMore::Combo(_) => Err(format!(
+                    "ambiguous key bindings, {} is the start of {}",
+                    fmt_keys(&sequence[..=depth]),
+                    fmt_keys(sequence)
+                )),
+                ActionOrMore::More(next) if last => {
+                    let mut longer = sequence.to_vec();
+                    longer.extend(any_sequence(next));
+                    Err(format!(
+                        "ambiguous key bindings, {} is the start of {}",
+                        fmt_keys(sequence),
+                        fmt_keys(&longer)
+                    ))
+                }
+                ActionOrMore::More(next) => build(sequence, depth + 1, next, action),
+            },
+            None => {
+                let next = if last {
+                    ActionOrMore::Combo(action)
+                } else {
+                    let mut more = vec![];
+                    build(sequence, depth + 1, &mut more, action)?;
+                    ActionOrMore::More(more)
+                };
+                tree.push(ComboNode { key, next });
+                Ok(())
             }
         }
     }
+
     let mut root = vec![];
     for (sequence, action) in sequences {
-        build(sequence.chars().peekable(), &mut root, action)
+        if !sequence.is_empty() {
+            build(&sequence, 0, &mut root, action)?;
+        }
     }
-    root
+    Ok(root)
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// The actions keys can be bound to, named in the config in snake case.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ComboAction {
-    Delete,
+    Up,
+    Down,
     SelectFirst,
-    Save,
+    SelectLast,
+    New,
+    Edit,
+    Delete,
     Yank,
+    Paste,
+    Undo,
+    Redo,
+    Save,
+    Quit,
+    StartTimer,
+    StopTimer,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    ToggleFilter,
+    ToggleStats,
+    Config,
+    DaysOff,
+    Holidays,
+    Issues,
+    History,
+    /// Repeats the last paste, delete or submitted edit.
+    Repeat,
+    CommandLine,
+    Help,
 }
 
-lazy_static! {
-    static ref COMBO_TREE: Vec<ComboNode> = build_combos([
-        ("dd", ComboAction::Delete),
-        ("gg", ComboAction::SelectFirst),
-        ("zz", ComboAction::Save),
-        ("yy", ComboAction::Yank),
-    ]);
+impl ComboAction {
+    /// Whether a count typed before the keys runs the action that many times.
+    pub fn takes_count(self) -> bool {
+        matches!(
+            self,
+            Self::Up
+                | Self::Down
+                | Self::Delete
+                | Self::Paste
+                | Self::Undo
+                | Self::Redo
+                | Self::NextMatch
+                | Self::PreviousMatch
+                | Self::Repeat
+        )
+    }
 }
 
+const DEFAULT_KEYMAP: [(&str, ComboAction); 28] = [
+    ("k", ComboAction::Up),
+    ("j", ComboAction::Down),
+    ("gg", ComboAction::SelectFirst),
+    ("G", ComboAction::SelectLast),
+    ("o", ComboAction::New),
+    ("e", ComboAction::Edit),
+    ("dd", ComboAction::Delete),
+    ("yy", ComboAction::Yank),
+    ("p", ComboAction::Paste),
+    ("u", ComboAction::Undo),
+    ("<C-r>", ComboAction::Redo),
+    ("zz", ComboAction::Save),
+    ("q", ComboAction::Quit),
+    ("t", ComboAction::StartTimer),
+    ("T", ComboAction::StopTimer),
+    ("/", ComboAction::Search),
+    ("n", ComboAction::NextMatch),
+    ("N", ComboAction::PreviousMatch),
+    ("F", ComboAction::ToggleFilter),
+    ("s", ComboAction::ToggleStats),
+    ("?", ComboAction::Config),
+    ("f", ComboAction::DaysOff),
+    ("h", ComboAction::Holidays),
+    ("i", ComboAction::Issues),
+    ("U", ComboAction::History),
+    (".", ComboAction::Repeat),
+    (":", ComboAction::CommandLine),
+    ("<F1>", ComboAction::Help),
+];
+
+/// The default key bindings with the ones in the config applied on top, bindings set to `None`
+/// are removed.
+fn keymap(
+    config: &BTreeMap<String, Option<ComboAction>>,
+) -> Result<Vec<(Vec<Key>, ComboAction)>, String> {
+    let mut bindings = DEFAULT_KEYMAP
+        .iter()
+        .map(|(keys, action)| Ok((parse_keys(keys)?, *action)))
+        .collect::<Result<Vec<_>, String>>()?;
+    for (keys, action) in config {
+        let keys =
+            parse_keys(keys).map_err(|e| format!("invalid key binding '{}': {}", keys, e))?;
+        bindings.retain(|(k, _)| *k != keys);
+        if let Some(action) = action {
+            bindings.push((keys, *action));
+        }
+    }
+    Ok(bindings)
+}
+
+/// The largest count, more would keep the app busy for a long time and be undone with many `u`.
+const MAX_COUNT: usize = 100;
+
 #[derive(Debug, Clone)]
 pub struct ComboBuffer {
-    cursor: &'static [ComboNode],
+    bindings: Vec<(Vec<Key>, ComboAction)>,
+    tree: Vec<ComboNode>,
+    /// The keys of the sequence being typed.
+    pending: Vec<Key>,
+    /// The count typed before the sequence, like the 3 in `3dd`.
+    count: Option<usize>,
 }
 
 impl Default for ComboBuffer {
     fn default() -> Self {
-        Self {
-            cursor: COMBO_TREE.as_slice(),
-        }
+        Self::new(&BTreeMap::new()).expect("the default keymap is valid")
     }
 }
 
 impl ComboBuffer {
-    pub fn combo(&mut self, k: KeyCode) -> Option<ComboAction> {
-        fn find(s: &[ComboNode], k: KeyCode) -> Option<&ActionOrMore> {
-            s.iter().find(|n| n.key == k).map(|n| &n.next)
+    /// A buffer for the keymap in the config.
+    pub fn new(config: &BTreeMap<String, Option<ComboAction>>) -> Result<Self, String> {
+        let bindings = keymap(config)?;
+        Ok(Self {
+            tree: build_combos(bindings.clone())?,
+            bindings,
+            pending: vec![],
+            count: None,
+        })
+    }
+
+    /// Feeds a key to the buffer, returning the action of the sequence it completes and how many
+    /// times to run it.
+    pub fn combo<K: Into<Key>>(&mut self, k: K) -> Option<(ComboAction, usize)> {
+        fn find<'a>(mut tree: &'a [ComboNode], keys: &[Key]) -> Option<&'a ActionOrMore> {
+            let mut found = None;
+            for k in keys {
+                let next = &tree.iter().find(|n| n.key == *k)?.next;
+                if let ActionOrMore::More(more) = next {
+                    tree = more;
+                }
+                found = Some(next);
+            }
+            found
         }
 
-        match find(self.cursor, k) {
+        let k = k.into();
+        // digits before a sequence are its count, a 0 only once the count started
+        if let (true, KeyCode::Char(c @ '0'..='9')) = (self.pending.is_empty(), k.code) {
+            if k.modifiers.is_empty() && (c != '0' || self.count.is_some()) {
+                let digit = c as usize - '0' as usize;
+                self.count = Some(
+                    self.count
+                        .unwrap_or(0)
+                        .saturating_mul(10)
+                        .saturating_add(digit),
+                );
+                return None;
+            }
+        }
+        self.pending.push(k);
+        match find(&self.tree, &self.pending) {
             Some(ActionOrMore::Combo(c)) => {
+                let combo = (*c, self.count.unwrap_or(1).clamp(1, MAX_COUNT));
                 self.reset();
-                return Some(*c);
+                Some(combo)
             }
-            Some(ActionOrMore::More(more)) => {
-                self.cursor = more;
+            Some(ActionOrMore::More(_)) => None,
+            // the key may start a new sequence
+            None if self.pending.len() > 1 => {
+                self.reset();
+                self.combo(k)
+            }
+            None => {
+                self.reset();
+                None
             }
-            None => match find(&COMBO_TREE, k) {
-                Some(ActionOrMore::Combo(c)) => {
-                    self.reset();
-                    return Some(*c);
-                }
-                Some(ActionOrMore::More(more)) => {
-                    self.cursor = more;
-                }
-                None => self.reset(),
-            },
         }
-        None
+    }
+
+    /// The actions in the keymap with the key sequences bound to them, in the order they were
+    /// bound.
+    pub fn bindings(&self) -> Vec<(ComboAction, Vec<String>)> {
+        let mut bindings: Vec<(ComboAction, Vec<String>)> = vec![];
+        for (keys, action) in &self.bindings {
+            match bindings.iter_mut().find(|(a, _)| a == action) {
+                Some((_, sequences)) => sequences.push(fmt_keys(keys)),
+                None => bindings.push((*action, vec![fmt_keys(keys)])),
+            }
+        }
+        bindings
     }
 
     pub fn reset(&mut self) {
-        *self = Self::default()
+        self.pending.clear();
+        self.count = None;
     }
 }
 
@@ -116,41 +428,47 @@ mod build_combo_test {
 
     use super::*;
 
+    fn combos<const N: usize>(
+        sequences: [(&str, ComboAction); N],
+    ) -> Result<Vec<ComboNode>, String> {
+        build_combos(sequences.map(|(s, a)| (parse_keys(s).unwrap(), a)))
+    }
+
     #[test]
     fn one_combo() {
         let expected = vec![ComboNode {
-            key: KeyCode::Char('d'),
+            key: Key::from(KeyCode::Char('d')),
             next: ActionOrMore::More(vec![ComboNode {
-                key: KeyCode::Char('d'),
+                key: Key::from(KeyCode::Char('d')),
                 next: ActionOrMore::Combo(ComboAction::Delete),
             }]),
         }];
 
-        assert_eq!(expected, build_combos([("dd", ComboAction::Delete)]))
+        assert_eq!(Ok(expected), combos([("dd", ComboAction::Delete)]))
     }
 
     #[test]
     fn two_independent_combos() {
         let expected = vec![
             ComboNode {
-                key: KeyCode::Char('d'),
+                key: Key::from(KeyCode::Char('d')),
                 next: ActionOrMore::More(vec![ComboNode {
-                    key: KeyCode::Char('d'),
+                    key: Key::from(KeyCode::Char('d')),
                     next: ActionOrMore::Combo(ComboAction::Delete),
                 }]),
             },
             ComboNode {
-                key: KeyCode::Char('g'),
+                key: Key::from(KeyCode::Char('g')),
                 next: ActionOrMore::More(vec![ComboNode {
-                    key: KeyCode::Char('g'),
+                    key: Key::from(KeyCode::Char('g')),
                     next: ActionOrMore::Combo(ComboAction::SelectFirst),
                 }]),
             },
         ];
 
         assert_eq!(
-            expected,
-            build_combos([
+            Ok(expected),
+            combos([
                 ("dd", ComboAction::Delete),
                 ("gg", ComboAction::SelectFirst)
             ])
@@ -161,30 +479,30 @@ mod build_combo_test {
     fn combos_with_common_start() {
         let expected = vec![
             ComboNode {
-                key: KeyCode::Char('d'),
+                key: Key::from(KeyCode::Char('d')),
                 next: ActionOrMore::More(vec![
                     ComboNode {
-                        key: KeyCode::Char('d'),
+                        key: Key::from(KeyCode::Char('d')),
                         next: ActionOrMore::Combo(ComboAction::Delete),
                     },
                     ComboNode {
-                        key: KeyCode::Char('i'),
+                        key: Key::from(KeyCode::Char('i')),
                         next: ActionOrMore::Combo(ComboAction::SelectFirst),
                     },
                 ]),
             },
             ComboNode {
-                key: KeyCode::Char('g'),
+                key: Key::from(KeyCode::Char('g')),
                 next: ActionOrMore::More(vec![ComboNode {
-                    key: KeyCode::Char('g'),
+                    key: Key::from(KeyCode::Char('g')),
                     next: ActionOrMore::Combo(ComboAction::SelectFirst),
                 }]),
             },
         ];
 
         assert_eq!(
-            expected,
-            build_combos([
+            Ok(expected),
+            combos([
                 ("dd", ComboAction::Delete),
                 ("di", ComboAction::SelectFirst),
                 ("gg", ComboAction::SelectFirst)
@@ -193,9 +511,57 @@ mod build_combo_test {
     }
 
     #[test]
-    #[should_panic]
     fn ambiguous_combos() {
-        build_combos([("dd", ComboAction::Delete), ("ddi", ComboAction::Delete)]);
+        assert_eq!(
+            combos([("dd", ComboAction::Delete), ("ddi", ComboAction::Delete)]),
+            Err("ambiguous key bindings, dd is the start of ddi".to_string())
+        );
+        assert_eq!(
+            combos([("ddi", ComboAction::Delete), ("d", ComboAction::Delete)]),
+            Err("ambiguous key bindings, d is the start of ddi".to_string())
+        );
+    }
+
+    #[test]
+    fn special_keys() {
+        let ctrl_r = Key {
+            code: KeyCode::Char('r'),
+            modifiers: KeyModifiers::CONTROL,
+        };
+        assert_eq!(parse_keys("<C-r>"), Ok(vec![ctrl_r]));
+        assert_eq!(
+            parse_keys("g<Esc><lt>"),
+            Ok(vec![
+                Key::from(KeyCode::Char('g')),
+                Key::from(KeyCode::Esc),
+                Key::from(KeyCode::Char('<'))
+            ])
+        );
+        assert_eq!(parse_keys("<"), Ok(vec![Key::from(KeyCode::Char('<'))]));
+        assert!(parse_keys("<C-nope>").is_err());
+        assert_eq!(fmt_keys(&parse_keys("<c-R>x<f5>").unwrap()), "<C-R>x<F5>");
+    }
+
+    #[test]
+    fn config_overrides_defaults() {
+        let config = [
+            ("dd".to_string(), None),
+            ("x".to_string(), Some(ComboAction::Delete)),
+        ]
+        .into_iter()
+        .collect();
+        let mut buf = ComboBuffer::new(&config).unwrap();
+        assert_eq!(
+            buf.combo(KeyCode::Char('x')),
+            Some((ComboAction::Delete, 1))
+        );
+        assert_eq!(buf.combo(KeyCode::Char('d')), None);
+        assert_eq!(buf.combo(KeyCode::Char('d')), None);
+
+        let config = [("g".to_string(), Some(ComboAction::Delete))]
+            .into_iter()
+            .collect();
+        assert!(ComboBuffer::new(&config).is_err());
     }
 }
 
@@ -208,14 +574,14 @@ mod test {
     fn dd() {
         let mut buf = ComboBuffer::default();
         assert_eq!(buf.combo(KeyCode::Char('d')), None);
-        assert_eq!(buf.combo(KeyCode::Char('d')), Some(Delete));
+        assert_eq!(buf.combo(KeyCode::Char('d')), Some((Delete, 1)));
     }
 
     #[test]
     fn gg() {
         let mut buf = ComboBuffer::default();
         assert_eq!(buf.combo(KeyCode::Char('g')), None);
-        assert_eq!(buf.combo(KeyCode::Char('g')), Some(SelectFirst));
+        assert_eq!(buf.combo(KeyCode::Char('g')), Some((SelectFirst, 1)));
     }
 
     #[test]
@@ -230,22 +596,47 @@ mod test {
         let mut buf = ComboBuffer::default();
         assert_eq!(buf.combo(KeyCode::Char('d')), None);
         assert_eq!(buf.combo(KeyCode::Char('g')), None);
-        assert_eq!(buf.combo(KeyCode::Char('g')), Some(SelectFirst));
+        assert_eq!(buf.combo(KeyCode::Char('g')), Some((SelectFirst, 1)));
     }
 
     #[test]
     fn combo_cleared_after_hit() {
         let mut buf = ComboBuffer::default();
         assert_eq!(buf.combo(KeyCode::Char('g')), None);
-        assert_eq!(buf.combo(KeyCode::Char('g')), Some(SelectFirst));
+        assert_eq!(buf.combo(KeyCode::Char('g')), Some((SelectFirst, 1)));
+        assert_eq!(buf.combo(KeyCode::Char('g')), None);
+    }
+
+    #[test]
+    fn counts() {
+        let mut buf = ComboBuffer::default();
+        assert_eq!(buf.combo(KeyCode::Char('3')), None);
+        assert_eq!(buf.combo(KeyCode::Char('d')), None);
+        assert_eq!(buf.combo(KeyCode::Char('d')), Some((Delete, 3)));
+        assert_eq!(buf.combo(KeyCode::Char('1')), None);
+        assert_eq!(buf.combo(KeyCode::Char('0')), None);
+        assert_eq!(buf.combo(KeyCode::Char('j')), Some((Down, 10)));
+        assert_eq!(buf.combo(KeyCode::Char('j')), Some((Down, 1)));
+        for c in "99999".chars() {
+            assert_eq!(buf.combo(KeyCode::Char(c)), None);
+        }
+        assert_eq!(buf.combo(KeyCode::Char('j')), Some((Down, MAX_COUNT)));
+    }
+
+    #[test]
+    fn count_dropped_with_invalid_sequence() {
+        let mut buf = ComboBuffer::default();
+        assert_eq!(buf.combo(KeyCode::Char('5')), None);
+        assert_eq!(buf.combo(KeyCode::Char('d')), None);
         assert_eq!(buf.combo(KeyCode::Char('g')), None);
+        assert_eq!(buf.combo(KeyCode::Char('g')), Some((SelectFirst, 1)));
     }
 
     #[test]
     fn stack_overflow() {
         let mut buf = ComboBuffer::default();
-        assert_eq!(buf.combo(KeyCode::Char('j')), None);
-        assert_eq!(buf.combo(KeyCode::Char('j')), None);
-        assert_eq!(buf.combo(KeyCode::Char('j')), None);
+        assert_eq!(buf.combo(KeyCode::Char('x')), None);
+        assert_eq!(buf.combo(KeyCode::Char('x')), None);
+        assert_eq!(buf.combo(KeyCode::Char('x')), None);
     }
 }
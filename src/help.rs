@@ -0,0 +1,208 @@
+use crate::app::{PopUp, COMMANDS};
+use crate::combo_buffer::{ComboAction, ComboBuffer};
+
+/// Where keys are pressed, they mean different things in each pop up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Context {
+    Table,
+    Form,
+    DaysOff,
+    Holidays,
+    Issues,
+    History,
+    Search,
+    CommandLine,
+    ConfirmQuit,
+    ExternalChange,
+}
+
+impl Context {
+    pub fn of(pop_up: Option<&PopUp>) -> Self {
+        match pop_up {
+            None => Self::Table,
+            Some(PopUp::EditingPopUp(_)) => Self::Form,
+            Some(PopUp::DaysOff { .. }) => Self::DaysOff,
+            Some(PopUp::Holidays { .. }) => Self::Holidays,
+            Some(PopUp::Issues { .. }) => Self::Issues,
+            Some(PopUp::History { .. }) => Self::History,
+            Some(PopUp::Search { .. }) => Self::Search,
+            Some(PopUp::CommandLine { .. }) => Self::CommandLine,
+            Some(PopUp::ConfirmQuit) => Self::ConfirmQuit,
+            Some(PopUp::ExternalChange) => Self::ExternalChange,
+            Some(PopUp::Help { under, .. }) => Self::of(under.as_deref()),
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Table => "help: activities",
+            Self::Form => "help: editing",
+            Self::DaysOff => "help: days off",
+            Self::Holidays => "help: holidays",
+            Self::Issues => "help: issues",
+            Self::History => "help: history",
+            Self::Search => "help: search",
+            Self::CommandLine => "help: command line",
+            Self::ConfirmQuit => "help: quitting",
+            Self::ExternalChange => "help: changed on disk",
+        }
+    }
+
+    /// What the actions of the keymap do here, `None` for the ones that do nothing.
+    fn describe(self, action: ComboAction) -> Option<&'static str> {
+        use ComboAction::*;
+        let what = match (self, action) {
+            (Self::Table, action) => describe_in_table(action),
+            (_, Help) => "show the keys of this pop up",
+            (Self::Search | Self::CommandLine, _) => return None,
+            (Self::ConfirmQuit | Self::ExternalChange, _) => return None,
+            (_, Quit) => "quit",
+            (_, Up) => "select the previous one",
+            (_, Down) => "select the next one",
+            (Self::DaysOff | Self::Holidays | Self::History, Undo) => "undo",
+            (Self::DaysOff | Self::Holidays | Self::History, Redo) => "redo",
+            (Self::DaysOff | Self::Holidays, New) => "add a date",
+            (Self::DaysOff | Self::Holidays, Edit) => "change the selected date",
+            (Self::DaysOff | Self::Holidays, Delete) => "remove the selected date",
+            (Self::DaysOff, DaysOff)
+            | (Self::Holidays, Holidays)
+            | (Self::Issues, Issues)
+            | (Self::History, History) => "close",
+            _ => return None,
+        };
+        Some(what)
+    }
+
+    /// The keys the event loop handles itself here, they aren't in the keymap.
+    fn fixed_keys(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::Table => &[(
+                "<count>",
+                "typed before a move, delete, paste, undo, redo or repeat runs it that many times",
+            )],
+            Self::Form => &[
+                ("i", "type in the selected field"),
+                (
+                    "<Tab>, <S-Tab>",
+                    "select the next or previous field while typing",
+                ),
+                ("<Enter>", "save the changes"),
+                ("<Esc>", "stop typing, or close without saving"),
+            ],
+            Self::DaysOff => &[
+                ("c", "change the kind of the selected day off"),
+                ("<Enter>", "add the typed date"),
+                ("<Esc>", "close, or stop typing a date"),
+            ],
+            Self::Holidays => &[
+                ("<Enter>", "add the typed date"),
+                ("<Esc>", "close, or stop typing a date"),
+            ],
+            Self::Issues => &[
+                ("s", "sort by the next column"),
+                ("r", "reverse the order"),
+                ("<Enter>", "select the last activity of the issue"),
+                ("<Esc>", "close"),
+            ],
+            Self::History => &[
+                ("<Enter>", "revert the selected change"),
+                ("<Esc>", "close"),
+            ],
+            Self::Search => &[
+                ("<Enter>", "search, the query is a regex"),
+                ("<Esc>", "cancel"),
+            ],
+            Self::CommandLine => &[("<Enter>", "run the command"), ("<Esc>", "cancel")],
+            Self::ConfirmQuit => &[
+                ("y", "save and quit"),
+                ("n", "quit without saving"),
+                ("<Esc>", "keep going"),
+            ],
+            Self::ExternalChange => &[
+                ("r", "reload the file, dropping the changes made here"),
+                ("m", "merge both changes"),
+                ("o", "overwrite the file with the changes made here"),
+                ("<Esc>", "keep going"),
+            ],
+        }
+    }
+}
+
+fn describe_in_table(action: ComboAction) -> &'static str {
+    use ComboAction::*;
+    match action {
+        Up => "select the previous activity",
+        Down => "select the next activity",
+        SelectFirst => "select the first activity",
+        SelectLast => "select the last activity",
+        New => "add an activity",
+        Edit => "edit the selected activity",
+        Delete => "delete the selected activity",
+        Yank => "copy the selected activity",
+        Paste => "paste after the selected activity",
+        Undo => "undo",
+        Redo => "redo",
+        Save => "save",
+        Quit => "quit",
+        StartTimer => "start a timer on the selected activity's issue",
+        StopTimer => "stop the running timer",
+        Search => "search",
+        NextMatch => "select the next match",
+        PreviousMatch => "select the previous match",
+        ToggleFilter => "show only the matches",
+        ToggleStats => "show the stats",
+        Config => "edit the config",
+        DaysOff => "show the days off",
+        Holidays => "show the holidays",
+        Issues => "show the issues",
+        History => "show the history",
+        Repeat => "repeat the last paste, delete or edit",
+        CommandLine => "open the command line",
+        Help => "show the keys",
+    }
+}
+
+/// The keys that do something in `context` and what they do, with the sequences bound in the
+/// keymap of `combos`.
+pub fn help(context: Context, combos: &ComboBuffer) -> Vec<(String, &'static str)> {
+    let mut lines = combos
+        .bindings()
+        .into_iter()
+        .filter_map(|(action, keys)| Some((keys.join(", "), context.describe(action)?)))
+        .chain(
+            context
+                .fixed_keys()
+                .iter()
+                .map(|(keys, what)| (keys.to_string(), *what)),
+        )
+        .collect::<Vec<_>>();
+    if context == Context::Table {
+        lines.extend(COMMANDS.iter().map(|(c, what)| (c.to_string(), *what)));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn follows_the_keymap() {
+        let config = [
+            ("x".to_string(), Some(ComboAction::Delete)),
+            ("f".to_string(), None),
+        ]
+        .into_iter()
+        .collect();
+        let combos = ComboBuffer::new(&config).unwrap();
+        let table = help(Context::Table, &combos);
+        assert!(table.contains(&("dd, x".to_string(), "delete the selected activity")));
+        assert!(!table.iter().any(|(_, what)| *what == "show the days off"));
+        let days_off = help(Context::DaysOff, &combos);
+        assert!(days_off.contains(&("dd, x".to_string(), "remove the selected date")));
+        assert!(days_off.contains(&("c".to_string(), "change the kind of the selected day off")));
+        assert!(!days_off.iter().any(|(_, what)| *what == "close"));
+        let search = help(Context::Search, &combos);
+        assert!(search.contains(&("<F1>".to_string(), "show the keys of this pop up")));
+    }
+}
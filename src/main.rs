@@ -1,28 +1,35 @@
 mod app;
+mod cli;
 mod combo_buffer;
+mod help;
 mod selected_vec;
 mod traits;
 mod ui;
 mod util;
 
-use app::PopUp;
-use combo_buffer::ComboBuffer;
+use app::{DayKind, ExCommand, PopUp};
+use clap::Parser;
+use cli::{Args, Command};
+use combo_buffer::{ComboAction, ComboBuffer};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use help::Context;
 
 use std::{
-    env::args,
     io::{self, Stdout},
+    time::Duration,
 };
 use tui::{
     backend::{Backend, CrosstermBackend},
     Terminal,
 };
 
-use crate::app::App;
+use crate::{app::App, ui::InfoPopup};
+
+const TICK_RATE: Duration = Duration::from_secs(1);
 
 fn setup_terminal() -> anyhow::Result<Terminal<CrosstermBackend<Stdout>>> {
     enable_raw_mode()?;
@@ -32,27 +39,40 @@ fn setup_terminal() -> anyhow::Result<Terminal<CrosstermBackend<Stdout>>> {
 }
 
 fn main() -> anyhow::Result<()> {
-    let path = match args().nth(1) {
-        Some(filename) => filename,
-        None => {
-            println!("Usage: {} [FILE]", args().next().unwrap());
-            std::process::exit(1)
-        }
+    let args = Args::parse();
+
+    let command = match args.command {
+        None if args.export => Some(Command::Export {
+            profile: None,
+            from: None,
+            to: None,
+        }),
+        command => command,
     };
-    let export = args()
-        .nth(2)
-        .filter(|s| s == "-e" || s == "--export")
-        .is_some();
 
-    let mut app = App::load(path)?;
-    if export {
-        match app.export() {
-            Ok(()) => println!("exported!"),
-            Err(e) => println!("failed to export: {:?}", e),
+    if let Some(command) = command {
+        let read_only = args.read_only || !command.writes();
+        let mut app = match App::load(args.file.clone(), read_only) {
+            // changes are merged with the ones of whoever has it open
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => App::load_unlocked(args.file)?,
+            res => res?,
+        };
+        if let Some(e) = config_warning(&app) {
+            eprintln!("{}", e);
         }
+        cli::run(&mut app, command)?;
     } else {
+        let (mut app, info_popup) = match App::load(args.file.clone(), args.read_only) {
+            // someone else is editing it, it can still be looked at
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => (
+                App::load(args.file, true)?,
+                Some(Err(format!("{}, opened read-only", e).into())),
+            ),
+            res => (res?, None),
+        };
+        let info_popup = info_popup.or_else(|| config_warning(&app).map(|e| Err(e.into())));
         let mut terminal = setup_terminal()?;
-        let res = run_app(&mut terminal, &mut app);
+        let res = run_app(&mut terminal, &mut app, info_popup);
 
         // restore terminal
         disable_raw_mode()?;
@@ -71,23 +91,93 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow::Result<()> {
-    let mut combo_buffer = ComboBuffer::default();
-    let mut info_popup = None;
+/// The warning shown when the config file couldn't be loaded.
+fn config_warning(app: &App) -> Option<String> {
+    app.config_error()
+        .map(|e| format!("invalid config, using the default one: {}", e))
+}
+
+/// The combo buffer for the keymap in the config, or the default one when it's invalid.
+fn build_combo_buffer(app: &App, info_popup: &mut InfoPopup) -> ComboBuffer {
+    match ComboBuffer::new(&app.config.keymap) {
+        Ok(combo_buffer) => combo_buffer,
+        Err(e) => {
+            *info_popup = Some(Err(format!(
+                "invalid keymap in the config, using the default one: {}",
+                e
+            )
+            .into()));
+            ComboBuffer::default()
+        }
+    }
+}
+
+fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    mut info_popup: InfoPopup,
+) -> anyhow::Result<()> {
+    let mut keymap = app.config.keymap.clone();
+    let mut combo_buffer = build_combo_buffer(app, &mut info_popup);
     loop {
+        // opening another file reads the config again
+        if app.config.keymap != keymap {
+            keymap = app.config.keymap.clone();
+            combo_buffer = build_combo_buffer(app, &mut info_popup);
+        }
+        app.save_if_due();
+        if app.changed_externally() && app.pop_up().is_none() {
+            app.show_external_change();
+        }
         terminal.draw(|f| ui::ui(f, app, &info_popup))?;
+        // wake up periodically so running timers keep ticking on screen
+        if !event::poll(TICK_RATE)? {
+            continue;
+        }
         info_popup = None;
         if let Event::Key(key) = event::read()? {
-            if let KeyCode::Char('q') = key.code {
-                if !app.editing() {
+            // keys typed into a field aren't bindings, the ones that can't be typed like <F1> can
+            // still show the help
+            let (action, count) = if app.editing() {
+                combo_buffer.reset();
+                let help = !matches!(key.code, KeyCode::Char(_))
+                    && matches!(combo_buffer.combo(key), Some((ComboAction::Help, _)));
+                combo_buffer.reset();
+                (help.then_some(ComboAction::Help), 1)
+            } else {
+                match combo_buffer.combo(key) {
+                    Some((action, count)) => (Some(action), count),
+                    None => (None, 1),
+                }
+            };
+            if action == Some(ComboAction::Help) {
+                if let Some(PopUp::Help { .. }) = app.pop_up() {
+                    app.hide_help();
+                } else {
+                    let context = Context::of(app.pop_up().as_ref());
+                    app.show_help(context.title(), help::help(context, &combo_buffer));
+                }
+                continue;
+            }
+            if action == Some(ComboAction::Quit)
+                && !matches!(app.pop_up(), Some(PopUp::ConfirmQuit))
+            {
+                if !app.is_dirty() {
                     return Ok(());
                 }
+                app.confirm_quit();
+                continue;
             }
             let n_days_off = app.n_days_off();
             let n_holidays = app.n_holidays();
+            let n_history = app.n_history();
+            let read_only = app.read_only();
+            let n_issues = match app.pop_up() {
+                Some(PopUp::Issues { .. }) => app.issues().len(),
+                _ => 0,
+            };
             match app.pop_up_mut() {
                 Some(PopUp::EditingPopUp(new)) => {
-                    combo_buffer.reset();
                     if new.is_editing() {
                         match key.code {
                             KeyCode::Char(c) => new.selected_buf().push(c),
@@ -105,12 +195,12 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow::Res
                             _ => {}
                         }
                     } else {
-                        match key.code {
-                            KeyCode::Char('i') => new.set_editing(true),
-                            KeyCode::Char('k') => new.select_prev(),
-                            KeyCode::Char('j') => new.select_next(),
-                            KeyCode::Esc => app.cancel_edit(),
-                            KeyCode::Enter => {
+                        match (key.code, action) {
+                            (KeyCode::Char('i'), _) => new.set_editing(true),
+                            (_, Some(ComboAction::Up)) => new.select_prev(),
+                            (_, Some(ComboAction::Down)) => new.select_next(),
+                            (KeyCode::Esc, _) => app.cancel_edit(),
+                            (KeyCode::Enter, _) => {
                                 if let Err(msg) = app.submit() {
                                     info_popup = Some(Err(msg.into()))
                                 }
@@ -122,6 +212,7 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow::Res
                 Some(app::PopUp::DaysOff {
                     selected,
                     new_day_off,
+                    ..
                 }) => {
                     if let Some(new_day_off) = new_day_off {
                         match key.code {
@@ -129,6 +220,7 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow::Res
                             KeyCode::Backspace => {
                                 new_day_off.pop();
                             }
+                            KeyCode::Esc => app.cancel_date_input(),
                             KeyCode::Enter => {
                                 if let Err(msg) = app.submit_new_day_off() {
                                     info_popup = Some(Err(msg.into()))
@@ -137,18 +229,42 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow::Res
                             _ => {}
                         }
                     } else {
-                        match key.code {
-                            KeyCode::Char('k') => *selected = selected.saturating_sub(1),
-                            KeyCode::Char('j') => *selected = (*selected + 1) % n_days_off,
-                            KeyCode::Char('o') => *new_day_off = Some(String::new()),
-                            KeyCode::Char('f') | KeyCode::Esc => app.hide_days_off(),
-                            _ => {}
+                        let res = match (key.code, action) {
+                            (KeyCode::Char('c'), _) => app.cycle_selected_day_off_kind(),
+                            (KeyCode::Esc, _) | (_, Some(ComboAction::DaysOff)) => {
+                                app.hide_days_off();
+                                Ok(())
+                            }
+                            (_, Some(ComboAction::Up)) => {
+                                *selected = selected.saturating_sub(1);
+                                Ok(())
+                            }
+                            (_, Some(ComboAction::Down)) => {
+                                *selected = (*selected + 1) % n_days_off.max(1);
+                                Ok(())
+                            }
+                            (_, Some(ComboAction::New)) if read_only => {
+                                Err("the file is open read-only")
+                            }
+                            (_, Some(ComboAction::New)) => {
+                                *new_day_off = Some(String::new());
+                                Ok(())
+                            }
+                            (_, Some(ComboAction::Edit)) => app.edit_selected_day_off(),
+                            (_, Some(ComboAction::Delete)) => app.delete_selected_day_off(),
+                            (_, Some(ComboAction::Undo)) => app.undo(),
+                            (_, Some(ComboAction::Redo)) => app.redo(),
+                            _ => Ok(()),
+                        };
+                        if let Err(msg) = res {
+                            info_popup = Some(Err(msg.into()))
                         }
                     }
                 }
                 Some(app::PopUp::Holidays {
                     selected,
                     new_holiday,
+                    ..
                 }) => {
                     if let Some(new_holiday) = new_holiday {
                         match key.code {
@@ -156,6 +272,7 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow::Res
                             KeyCode::Backspace => {
                                 new_holiday.pop();
                             }
+                            KeyCode::Esc => app.cancel_date_input(),
                             KeyCode::Enter => {
                                 if let Err(msg) = app.submit_new_holiday() {
                                     info_popup = Some(Err(msg.into()))
@@ -164,63 +281,322 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> anyhow::Res
                             _ => {}
                         }
                     } else {
-                        match key.code {
-                            KeyCode::Char('k') => *selected = selected.saturating_sub(1),
-                            KeyCode::Char('j') => *selected = (*selected + 1) % n_holidays,
-                            KeyCode::Char('o') => *new_holiday = Some(String::new()),
-                            KeyCode::Char('h') | KeyCode::Esc => app.hide_holidays(),
-                            _ => {}
+                        let res = match (key.code, action) {
+                            (KeyCode::Esc, _) | (_, Some(ComboAction::Holidays)) => {
+                                app.hide_holidays();
+                                Ok(())
+                            }
+                            (_, Some(ComboAction::Up)) => {
+                                *selected = selected.saturating_sub(1);
+                                Ok(())
+                            }
+                            (_, Some(ComboAction::Down)) => {
+                                *selected = (*selected + 1) % n_holidays.max(1);
+                                Ok(())
+                            }
+                            (_, Some(ComboAction::New)) if read_only => {
+                                Err("the file is open read-only")
+                            }
+                            (_, Some(ComboAction::New)) => {
+                                *new_holiday = Some(String::new());
+                                Ok(())
+                            }
+                            (_, Some(ComboAction::Edit)) => app.edit_selected_holiday(),
+                            (_, Some(ComboAction::Delete)) => app.delete_selected_holiday(),
+                            (_, Some(ComboAction::Undo)) => app.undo(),
+                            (_, Some(ComboAction::Redo)) => app.redo(),
+                            _ => Ok(()),
+                        };
+                        if let Err(msg) = res {
+                            info_popup = Some(Err(msg.into()))
                         }
                     }
                 }
-                None => {
-                    match key.code {
-                        KeyCode::Char('k') => app.previous(),
-                        KeyCode::Char('j') => app.next(),
-                        KeyCode::Char('s') => app.toggle_stats(),
-                        KeyCode::Char('o') => app.create_new_activity(),
-                        KeyCode::Char('?') => app.edit_config(),
-                        KeyCode::Char('u') => app.undo(),
-                        KeyCode::Char('r') if key.modifiers == KeyModifiers::CONTROL => app.redo(),
-                        KeyCode::Char('e') => app.edit_activity(),
-                        KeyCode::Char('G') => app.select_last(),
-                        KeyCode::Char('f') => app.show_days_off(),
-                        KeyCode::Char('h') => app.show_holidays(),
-                        KeyCode::Char('p') => {
-                            if let Err(msg) = app.paste() {
-                                info_popup = Some(Err(msg.into()))
+                Some(PopUp::Issues {
+                    selected,
+                    sort,
+                    descending,
+                }) => match (key.code, action) {
+                    (KeyCode::Char('s'), _) => *sort = sort.next(),
+                    (KeyCode::Char('r'), _) => *descending = !*descending,
+                    (KeyCode::Enter, _) => app.jump_to_selected_issue(),
+                    (KeyCode::Esc, _) | (_, Some(ComboAction::Issues)) => app.hide_issues(),
+                    (_, Some(ComboAction::Up)) => *selected = selected.saturating_sub(1),
+                    (_, Some(ComboAction::Down)) => *selected = (*selected + 1) % n_issues.max(1),
+                    _ => {}
+                },
+                Some(PopUp::History { selected }) => {
+                    let res = match (key.code, action) {
+                        (KeyCode::Enter, _) => app.revert_selected_history_entry(),
+                        (KeyCode::Esc, _) | (_, Some(ComboAction::History)) => {
+                            app.hide_history();
+                            Ok(())
+                        }
+                        (_, Some(ComboAction::Up)) => {
+                            *selected = selected.saturating_sub(1);
+                            Ok(())
+                        }
+                        (_, Some(ComboAction::Down)) => {
+                            *selected = (*selected + 1) % n_history.max(1);
+                            Ok(())
+                        }
+                        (_, Some(ComboAction::Undo)) => app.undo(),
+                        (_, Some(ComboAction::Redo)) => app.redo(),
+                        _ => Ok(()),
+                    };
+                    if let Err(msg) = res {
+                        info_popup = Some(Err(msg.into()))
+                    }
+                }
+                Some(PopUp::Search { query }) => match key.code {
+                    KeyCode::Char(c) => query.push(c),
+                    KeyCode::Backspace => {
+                        query.pop();
+                    }
+                    KeyCode::Esc => app.cancel_edit(),
+                    KeyCode::Enter => {
+                        if let Err(msg) = app.submit_search() {
+                            info_popup = Some(Err(msg.into()))
+                        }
+                    }
+                    _ => {}
+                },
+                Some(PopUp::CommandLine { line }) => match key.code {
+                    KeyCode::Char(c) => line.push(c),
+                    // like in vim, deleting past the start closes it
+                    KeyCode::Backspace if line.is_empty() => app.cancel_edit(),
+                    KeyCode::Backspace => {
+                        line.pop();
+                    }
+                    KeyCode::Esc => app.cancel_edit(),
+                    KeyCode::Enter => {
+                        let line = app.take_command_line().unwrap_or_default();
+                        match line.parse() {
+                            Ok(command) => match run_command(app, command, &combo_buffer) {
+                                Outcome::Quit => return Ok(()),
+                                Outcome::Continue(popup) => info_popup = popup,
+                            },
+                            Err(msg) => {
+                                info_popup = Some(Err(format!(":{}: {}", line.trim(), msg).into()))
                             }
                         }
-                        _ => {}
                     }
-                    if let Some(combo) = combo_buffer.combo(key.code) {
-                        match combo {
-                            combo_buffer::ComboAction::Delete => {
-                                app.delete_activity();
-                            }
-                            combo_buffer::ComboAction::SelectFirst => {
-                                app.select_first();
-                            }
-                            combo_buffer::ComboAction::Save => {
-                                match app.save() {
-                                    Ok(_) => info_popup = Some(Ok("saved successfully".into())),
-                                    Err(e) => {
-                                        info_popup =
-                                            Some(Err(format!("failed to save: {}", e).into()))
-                                    }
-                                };
-                            }
-                            combo_buffer::ComboAction::Yank => {
-                                info_popup = if app.yank_selected() {
-                                    Some(Ok("yanked!".into()))
-                                } else {
-                                    Some(Err("nothing selected".into()))
-                                };
+                    _ => {}
+                },
+                Some(PopUp::Help {
+                    lines, selected, ..
+                }) => match (key.code, action) {
+                    (KeyCode::Esc | KeyCode::Enter, _) => app.hide_help(),
+                    (_, Some(ComboAction::Up)) => *selected = selected.saturating_sub(1),
+                    (_, Some(ComboAction::Down)) => {
+                        *selected = (*selected + 1).min(lines.len().saturating_sub(1))
+                    }
+                    _ => {}
+                },
+                Some(PopUp::ConfirmQuit) => match key.code {
+                    KeyCode::Char('y') => match app.save() {
+                        Ok(()) => return Ok(()),
+                        Err(e) => {
+                            app.hide_confirm_quit();
+                            info_popup = Some(Err(format!("failed to save: {}", e).into()))
+                        }
+                    },
+                    KeyCode::Char('n') => {
+                        app.discard_changes();
+                        return Ok(());
+                    }
+                    KeyCode::Esc => app.hide_confirm_quit(),
+                    _ => {}
+                },
+                Some(PopUp::ExternalChange) => {
+                    let res = match key.code {
+                        KeyCode::Char('r') => app.reload(),
+                        KeyCode::Char('m') => app.merge_external_changes(),
+                        KeyCode::Char('o') => app.overwrite_external_changes(),
+                        KeyCode::Esc => {
+                            app.hide_external_change();
+                            Ok(())
+                        }
+                        _ => Ok(()),
+                    };
+                    if let Err(e) = res {
+                        info_popup = Some(Err(e.to_string().into()))
+                    }
+                }
+                None => {
+                    if let Some(action) = action {
+                        let count = if action.takes_count() { count } else { 1 };
+                        app.start_repeat();
+                        for _ in 0..count {
+                            info_popup = run_action(app, action);
+                            if let Some(Err(_)) = info_popup {
+                                break;
                             }
                         }
+                        app.end_repeat();
                     }
                 }
             }
         }
     }
 }
+
+/// Runs an action bound to a key in the main view, returning what to tell about it.
+fn run_action(app: &mut App, action: ComboAction) -> InfoPopup {
+    let res = match action {
+        ComboAction::Up => {
+            app.previous();
+            Ok(())
+        }
+        ComboAction::Down => {
+            app.next();
+            Ok(())
+        }
+        ComboAction::SelectFirst => {
+            app.select_first();
+            Ok(())
+        }
+        ComboAction::SelectLast => {
+            app.select_last();
+            Ok(())
+        }
+        ComboAction::New => app.create_new_activity(),
+        ComboAction::Edit => app.edit_activity(),
+        ComboAction::Delete => app.delete_activity(),
+        ComboAction::Yank => {
+            return if app.yank_selected() {
+                Some(Ok("yanked!".into()))
+            } else {
+                Some(Err("nothing selected".into()))
+            };
+        }
+        ComboAction::Paste => app.paste(),
+        ComboAction::Undo => app.undo(),
+        ComboAction::Redo => app.redo(),
+        ComboAction::Save => {
+            return match app.save() {
+                Ok(_) => Some(Ok("saved successfully".into())),
+                Err(e) => Some(Err(format!("failed to save: {}", e).into())),
+            };
+        }
+        // handled before the pop-ups, it works in most of them
+        ComboAction::Quit => Ok(()),
+        ComboAction::StartTimer => app.start_timer(),
+        ComboAction::StopTimer => app.stop_timer(),
+        ComboAction::Search => {
+            app.start_search();
+            Ok(())
+        }
+        ComboAction::NextMatch => app.next_match(),
+        ComboAction::PreviousMatch => app.previous_match(),
+        ComboAction::ToggleFilter => app.toggle_filter(),
+        ComboAction::ToggleStats => {
+            app.toggle_stats();
+            Ok(())
+        }
+        ComboAction::Config => app.edit_config(),
+        ComboAction::DaysOff => {
+            app.show_days_off();
+            Ok(())
+        }
+        ComboAction::Holidays => {
+            app.show_holidays();
+            Ok(())
+        }
+        ComboAction::Issues => {
+            app.show_issues();
+            Ok(())
+        }
+        ComboAction::History => {
+            app.show_history();
+            Ok(())
+        }
+        ComboAction::Repeat => app.repeat_last_change(),
+        ComboAction::CommandLine => {
+            app.start_command_line();
+            Ok(())
+        }
+        // handled before the pop-ups, it works in all of them
+        ComboAction::Help => Ok(()),
+    };
+    res.err().map(|msg| Err(msg.into()))
+}
+
+/// What the event loop does after a command.
+enum Outcome {
+    Continue(InfoPopup),
+    Quit,
+}
+
+/// Runs a command typed on the command line.
+fn run_command(app: &mut App, command: ExCommand, combos: &ComboBuffer) -> Outcome {
+    let done = |res: Result<(), &'static str>| Outcome::Continue(res.err().map(|e| Err(e.into())));
+    let unsaved = |app: &App, force: bool| app.is_dirty() && !force;
+    match command {
+        ExCommand::Write { file: None, .. } => Outcome::Continue(Some(match app.save() {
+            Ok(()) => Ok("saved successfully".into()),
+            Err(e) => Err(format!("failed to save: {}", e).into()),
+        })),
+        ExCommand::Write {
+            file: Some(file),
+            force,
+        } => Outcome::Continue(Some(match app.write_copy(&file, force) {
+            Ok(()) => Ok(format!("written to {}", file).into()),
+            Err(e) => Err(format!("failed to write: {}", e).into()),
+        })),
+        ExCommand::WriteQuit => match app.save() {
+            Ok(()) => Outcome::Quit,
+            Err(e) => Outcome::Continue(Some(Err(format!("failed to save: {}", e).into()))),
+        },
+        ExCommand::Quit { force } if unsaved(app, force) => done(Err(
+            "there are unsaved changes, save them with :w or drop them with :q!",
+        )),
+        ExCommand::Quit { .. } => {
+            app.discard_changes();
+            Outcome::Quit
+        }
+        ExCommand::Edit { force, .. } if unsaved(app, force) => done(Err(
+            "there are unsaved changes, save them with :w or drop them with :e!",
+        )),
+        ExCommand::Edit { file, .. } => {
+            let file = file.unwrap_or_else(|| app.filename().to_string());
+            if file == app.filename() {
+                return Outcome::Continue(app.reload().err().map(|e| Err(e.to_string().into())));
+            }
+            match App::load(file.clone(), app.read_only()) {
+                Ok(other) => {
+                    app.discard_changes();
+                    *app = other;
+                    Outcome::Continue(Some(match config_warning(app) {
+                        Some(e) => Err(e.into()),
+                        None => Ok(format!("opened {}", file).into()),
+                    }))
+                }
+                Err(e) => Outcome::Continue(Some(Err(format!("failed to open: {}", e).into()))),
+            }
+        }
+        ExCommand::Export { profile } => {
+            let profile = app.config.export_profile(profile.as_deref());
+            let res = match profile.as_ref().map(|p| (p, p.output_path(app.filename()))) {
+                Some((profile, Some(path))) => app
+                    .export(profile, None, None)
+                    .map(|_| format!("exported to {}", path))
+                    .map_err(|e| format!("failed to export: {}", e)),
+                // it would be drawn over
+                Some((_, None)) => {
+                    Err("the profile exports to stdout, use the export command".into())
+                }
+                None => Err("no export profile with that name".to_string()),
+            };
+            Outcome::Continue(Some(res.map(Into::into).map_err(Into::into)))
+        }
+        ExCommand::Goto(day) => done(app.goto_day(day)),
+        ExCommand::Holiday { add, day } => done(app.edit_calendar(day, DayKind::Holiday, add)),
+        ExCommand::DayOff { add, day, kind } => done(app.edit_calendar(day, kind, add)),
+        ExCommand::Set { key, value } => done(app.set_config(&key, value)),
+        ExCommand::Help => {
+            app.show_help(Context::Table.title(), help::help(Context::Table, combos));
+            Outcome::Continue(None)
+        }
+    }
+}
@@ -1,4 +1,3 @@
-use crate::App;
 use std::any::Any;
 
 pub trait EditingPopUp {
@@ -7,7 +6,6 @@ pub trait EditingPopUp {
     fn select_next(&mut self);
     fn select_prev(&mut self);
     fn selected_buf(&mut self) -> &mut String;
-    fn submit(&self, app: &mut App) -> Result<(), &'static str>;
     fn render(&self) -> Vec<tui::widgets::Paragraph<'_>>;
     fn popup_type(&self) -> crate::app::PopUpType;
     fn as_any(&self) -> &dyn Any;
@@ -1,6 +1,6 @@
-use std::{borrow::Cow, iter::repeat};
+use std::{borrow::Cow, collections::BTreeMap, io, iter::repeat_n};
 
-use time::Duration;
+use time::{Duration, PrimitiveDateTime};
 use tui::{
     backend::Backend,
     layout::{Constraint, Direction, Layout, Rect},
@@ -14,38 +14,52 @@ use tui::{
 };
 
 use crate::{
-    app::{Activity, App, PopUp},
+    app::{Activity, Allowance, App, Balance, DayKind, IssueColumn, PopUp, Summary},
     selected_vec::SelectedVec,
     traits::EditingPopUp,
     util::{
-        fmt_duration, is_weekend, size_slice,
-        time_fmt::{DATE_FMT_FULL, TIME_FMT},
+        self, fmt_balance, fmt_days, fmt_duration, is_weekend, size_slice,
+        time_fmt::{DATE_FMT, DATE_FMT_FULL, TIME_FMT},
+        Zone,
     },
 };
 
 impl Activity {
-    fn to_row(&self) -> Row {
-        let action = self.action.clone();
+    fn to_row(&self, now: PrimitiveDateTime, home: &Zone) -> Row<'_> {
+        // the table is on the home wall clock, activities recorded elsewhere show their local start
+        let action = match self.offset {
+            Some(offset) => format!(
+                "{} ({} at UTC{})",
+                self.action,
+                util::convert(self.start(), home, &Zone::fixed(offset))
+                    .time()
+                    .format(TIME_FMT)
+                    .unwrap(),
+                util::fmt_offset(offset)
+            ),
+            None => self.action.clone(),
+        };
         let issue = self.issue.clone();
         let start = self.start_time.format(TIME_FMT).unwrap();
-        let end = self
-            .end_time
-            .map(|t| t.format(TIME_FMT).unwrap())
-            .unwrap_or_else(|| "None".to_string());
-        let time_spent = self
-            .end_time
-            .map(|t| t - self.start_time)
-            .map(fmt_duration)
-            .unwrap_or_else(String::new);
-
-        Row::new([action, issue, start, end, time_spent])
+        let running = self.is_running(now);
+        let end = match self.end_time {
+            Some(t) if self.crosses_midnight() => format!("{} +1", t.format(TIME_FMT).unwrap()),
+            Some(t) => t.format(TIME_FMT).unwrap(),
+            None if running => "running".to_string(),
+            None => "None".to_string(),
+        };
+        let time_spent = self.time_spent(now).map(fmt_duration).unwrap_or_default();
+
+        let row = Row::new([action, issue, start, end, time_spent]);
+        if running {
+            row.style(Style::default().fg(Color::Green))
+        } else {
+            row
+        }
     }
 
-    fn distance(&self, next: &Activity) -> Option<Row> {
-        let bubble_start = match self.end_time {
-            Some(s) => s,
-            None => return None,
-        };
+    fn distance(&self, next: &Activity) -> Option<Row<'_>> {
+        let bubble_start = self.end_time.filter(|_| !self.crosses_midnight())?;
         let bubble_end = next.start_time;
         let bubble_length = bubble_end - bubble_start;
         bubble_length.is_positive().then(|| {
@@ -61,51 +75,43 @@ impl Activity {
 }
 
 struct Stats {
-    month_time: Duration,
-    work_days: u16,
+    summary: Summary,
+    balance: io::Result<Balance>,
     workdays_worked: u32,
     weekend_days_worked: u32,
     holiday_days_worked: u32,
-    days_off: u16,
-    work_day_hours: f32,
+    days_off: BTreeMap<DayKind, f32>,
+    allowance: io::Result<Option<Allowance>>,
     time_spent_on_issue: Option<Duration>,
 }
 
 fn render_table<B: Backend>(frame: &mut Frame<B>, rect: Rect, app: &App) -> Stats {
-    let mut month_time = Duration::ZERO;
     let mut workdays_worked = 0;
     let mut weekend_worked_days = 0;
     let mut holiday_worked_days = 0;
     let selected_id = app.selected_id();
+    let now = app.config.home_zone.now();
     let items: SelectedVec<_> = app
-        .activities()
-        .filter(|(_, acts)| !acts.is_empty())
+        .days(now)
+        .into_iter()
         .flat_map(|(date, acts)| {
-            if is_weekend(date) {
+            let is_selected = |a: &Activity| Some(a.id) == selected_id;
+            let acts = acts
+                .iter()
+                .filter(|a| app.is_visible(a))
+                .collect::<Vec<_>>();
+            let (total_time, some_none) = app.time_spent_on(date, now);
+            if acts.is_empty() && total_time.is_zero() {
+                return Vec::new();
+            }
+            if is_weekend(&date) {
                 weekend_worked_days += 1;
-            } else if app.is_free_holiday(date) {
+            } else if app.is_free_holiday(&date) {
                 holiday_worked_days += 1;
             } else {
                 workdays_worked += 1;
             }
-            let is_selected = |a: &Activity| Some(a.id) == selected_id;
-
-            let (total_time, some_none) = {
-                let mut some_none = false;
-                let total_time = acts
-                    .iter()
-                    .filter_map(|a| {
-                        if let Some(end_time) = a.end_time {
-                            Some(end_time - a.start_time)
-                        } else {
-                            some_none = true;
-                            None
-                        }
-                    })
-                    .sum();
-                month_time += total_time;
-                (fmt_duration(total_time), some_none)
-            };
+            let total_time = fmt_duration(total_time);
 
             let separator = Row::new([
                 Cell::from(date.format(DATE_FMT_FULL).unwrap()),
@@ -120,9 +126,9 @@ fn render_table<B: Backend>(frame: &mut Frame<B>, rect: Rect, app: &App) -> Stat
             ])
             .style(
                 Style::default()
-                    .bg(if is_weekend(date) {
+                    .bg(if is_weekend(&date) {
                         Color::Red
-                    } else if app.is_free_holiday(date) {
+                    } else if app.is_free_holiday(&date) {
                         Color::Yellow
                     } else {
                         Color::Blue
@@ -131,19 +137,24 @@ fn render_table<B: Backend>(frame: &mut Frame<B>, rect: Rect, app: &App) -> Stat
                     .add_modifier(Modifier::BOLD),
             );
 
-            let interspersed = acts.windows(2).map(size_slice).flat_map(move |[a, next]| {
-                let mut iteration = vec![(a.to_row(), is_selected(a))];
-                if let Some(bubble) = a.distance(next) {
+            // the gaps between activities are meaningless when some of them are hidden
+            let show_gaps = !app.is_filtering();
+            let interspersed = acts.windows(2).map(size_slice).flat_map(|&[a, next]| {
+                let mut iteration = vec![(a.to_row(now, &app.config.home_zone), is_selected(a))];
+                if let Some(bubble) = a.distance(next).filter(|_| show_gaps) {
                     iteration.push((bubble, false))
                 }
                 iteration
             });
 
-            let last = acts.last().map(|a| (a.to_row(), is_selected(a)));
+            let last = acts
+                .last()
+                .map(|a| (a.to_row(now, &app.config.home_zone), is_selected(a)));
 
             std::iter::once((separator, false))
                 .chain(interspersed)
                 .chain(last)
+                .collect::<Vec<_>>()
         })
         .collect();
 
@@ -151,11 +162,28 @@ fn render_table<B: Backend>(frame: &mut Frame<B>, rect: Rect, app: &App) -> Stat
 
     let items = Table::new(items)
         .header(Row::new([
-            "Action",
-            "Issue",
-            "start time",
-            "end time",
-            "time spent",
+            Cell::from(Spans::from(vec![
+                Span::styled(
+                    if app.read_only() { "RO " } else { "" },
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    if app.is_dirty() { "[+] " } else { "" },
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(match app.search() {
+                    Some(search) if app.is_filtering() => {
+                        format!("Action (filter: {})", search.query())
+                    }
+                    _ => "Action".to_string(),
+                }),
+            ])),
+            Cell::from("Issue"),
+            Cell::from("start time"),
+            Cell::from("end time"),
+            Cell::from("time spent"),
         ]))
         .block(Block::default())
         .highlight_style(
@@ -178,14 +206,15 @@ fn render_table<B: Backend>(frame: &mut Frame<B>, rect: Rect, app: &App) -> Stat
         state.select(index);
         state
     });
+    let summary = app.summary();
     Stats {
-        month_time,
-        work_days: app.n_workdays_so_far(),
+        balance: app.balance(&summary),
+        summary,
         workdays_worked,
         weekend_days_worked: weekend_worked_days,
         holiday_days_worked: holiday_worked_days,
-        days_off: app.n_days_off_up_to_today(),
-        work_day_hours: app.config.work_day_hours,
+        days_off: app.days_off_up_to_today(),
+        allowance: app.allowance(),
         time_spent_on_issue: app.selected_issue_total_time(),
     }
 }
@@ -202,6 +231,7 @@ pub fn ui<B: Backend>(frame: &mut Frame<B>, app: &mut App, info_popup: &InfoPopu
         Some(PopUp::DaysOff {
             selected,
             new_day_off,
+            ..
         }) => {
             render_table(frame, main, app);
             render_days_off(frame, main, app, *selected, new_day_off);
@@ -209,10 +239,88 @@ pub fn ui<B: Backend>(frame: &mut Frame<B>, app: &mut App, info_popup: &InfoPopu
         Some(PopUp::Holidays {
             selected,
             new_holiday,
+            ..
         }) => {
             render_table(frame, main, app);
             render_holidays(frame, main, app, *selected, new_holiday);
         }
+        Some(PopUp::Issues {
+            selected,
+            sort,
+            descending,
+        }) => {
+            render_table(frame, main, app);
+            render_issues(frame, main, app, *selected, *sort, *descending);
+        }
+        Some(PopUp::History { selected }) => {
+            render_table(frame, main, app);
+            render_history(frame, main, app, *selected);
+        }
+        Some(PopUp::Search { query }) => {
+            render_table(frame, main, app);
+            let bottom = bottom_of_rect(main, 3);
+            frame.render_widget(Clear, bottom);
+            frame.render_widget(
+                Paragraph::new(format!("/{}", query))
+                    .style(Style::default().fg(Color::Yellow))
+                    .block(Block::default().borders(Borders::ALL).title("search")),
+                bottom,
+            );
+        }
+        Some(PopUp::CommandLine { line }) => {
+            render_table(frame, main, app);
+            let bottom = bottom_of_rect(main, 3);
+            frame.render_widget(Clear, bottom);
+            frame.render_widget(
+                Paragraph::new(format!(":{}", line))
+                    .style(Style::default().fg(Color::Yellow))
+                    .block(Block::default().borders(Borders::ALL).title("command")),
+                bottom,
+            );
+        }
+        Some(PopUp::Help {
+            title,
+            lines,
+            selected,
+            ..
+        }) => {
+            render_table(frame, main, app);
+            render_help(frame, main, title, lines, *selected);
+        }
+        Some(PopUp::ExternalChange) => {
+            render_table(frame, main, app);
+            render_prompt(
+                frame,
+                main,
+                "changed on disk",
+                vec![
+                    Spans::from(format!(
+                        "{} was changed by another program since it was loaded.",
+                        app.filename()
+                    )),
+                    Spans::from(""),
+                    Spans::from("r: reload it, dropping the changes made here"),
+                    Spans::from("m: merge both changes, keeping the ones made here on conflict"),
+                    Spans::from("o: overwrite it with the changes made here"),
+                    Spans::from("Esc: do nothing for now"),
+                ],
+            );
+        }
+        Some(PopUp::ConfirmQuit) => {
+            render_table(frame, main, app);
+            render_prompt(
+                frame,
+                main,
+                "unsaved changes",
+                vec![
+                    Spans::from("Save the changes before quitting?"),
+                    Spans::from(""),
+                    Spans::from("y: save and quit"),
+                    Spans::from("n: quit without saving"),
+                    Spans::from("Esc: don't quit"),
+                ],
+            );
+        }
         None => {
             let stats_height = app
                 .show_stats()
@@ -263,9 +371,11 @@ fn render_new_popup<B: Backend>(frame: &mut Frame<B>, rect: Rect, new: &dyn Edit
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints(
-            repeat(Constraint::Length(new_act_sizes::WIDGET_HEIGHT))
-                .take(new_act_sizes::NUM_WIDGETS.into())
-                .collect::<Vec<_>>(),
+            repeat_n(
+                Constraint::Length(new_act_sizes::WIDGET_HEIGHT),
+                new_act_sizes::NUM_WIDGETS.into(),
+            )
+            .collect::<Vec<_>>(),
         )
         .split(bottom);
     frame.render_widget(Clear, bottom);
@@ -276,20 +386,20 @@ fn render_new_popup<B: Backend>(frame: &mut Frame<B>, rect: Rect, new: &dyn Edit
 }
 
 mod stats_size {
-    pub(super) const TOTAL_HEIGHT: u16 = 9;
+    pub(super) const TOTAL_HEIGHT: u16 = 11;
 }
 
 fn render_stats<B: Backend>(
     frame: &mut Frame<B>,
     rect: Rect,
     Stats {
-        month_time,
-        work_days,
+        summary,
+        balance,
         workdays_worked,
         weekend_days_worked,
         holiday_days_worked,
         days_off,
-        work_day_hours,
+        allowance,
         time_spent_on_issue,
     }: Stats,
 ) {
@@ -298,24 +408,22 @@ fn render_stats<B: Backend>(
         .border_type(BorderType::Thick)
         .title("Stats");
     let legend_style = Style::default().add_modifier(Modifier::BOLD);
-    let worked_days = work_days.saturating_sub(days_off);
+    let worked_days = summary.days_worked();
     let table = Table::new(vec![
         Row::new([
             Span::styled("Total time this month: ", legend_style),
-            Span::raw(fmt_duration(month_time)),
+            Span::raw(fmt_duration(summary.worked)),
         ]),
         Row::new([
             Span::styled("Average time per work day: ", legend_style),
-            Span::raw(fmt_duration(
-                month_time
-                    .checked_div(worked_days.into())
-                    .unwrap_or_default(),
-            )),
+            Span::raw(fmt_duration(if worked_days > 0.0 {
+                summary.worked / worked_days
+            } else {
+                Duration::ZERO
+            })),
         ]),
         {
-            let work_day_mins = (work_day_hours * 60.0) as u16;
-            let otime = work_day_mins * worked_days;
-            let overtime = month_time - Duration::minutes(otime.into());
+            let overtime = summary.overtime();
             let (legend, dur, legend_style) = if overtime.is_negative() {
                 (
                     "Undertime hours:",
@@ -330,9 +438,27 @@ fn render_stats<B: Backend>(
                 Span::raw(fmt_duration(dur)),
             ])
         },
+        match balance {
+            Ok(balance) => {
+                let closing = balance.closing();
+                let color = if closing.is_negative() {
+                    Color::Red
+                } else {
+                    Color::Green
+                };
+                Row::new([
+                    Span::styled("Running balance:", legend_style.fg(color)),
+                    Span::raw(fmt_balance(closing)),
+                ])
+            }
+            Err(e) => Row::new([
+                Span::styled("Running balance:", legend_style.fg(Color::Red)),
+                Span::raw(e.to_string()),
+            ]),
+        },
         Row::new([
             Span::styled("Total work days: ", legend_style),
-            Span::raw(work_days.to_string()),
+            Span::raw(summary.work_days.to_string()),
         ]),
         Row::new([
             Spans::from(vec![Span::styled("Total worked days: ", legend_style)]),
@@ -360,7 +486,31 @@ fn render_stats<B: Backend>(
         ]),
         Row::new([
             Span::styled("Days off: ", legend_style),
-            Span::raw(days_off.to_string()),
+            Span::raw({
+                if days_off.is_empty() {
+                    "0".to_string()
+                } else {
+                    let total = days_off.values().sum::<f32>();
+                    let kinds = days_off
+                        .iter()
+                        .map(|(kind, days)| format!("{} {}", kind, days))
+                        .collect::<Vec<_>>();
+                    format!("{} ({})", total, kinds.join(", "))
+                }
+            }),
+        ]),
+        Row::new([
+            Span::styled("Vacation: ", legend_style),
+            Span::raw(match allowance {
+                Ok(Some(a)) => format!(
+                    "{} used, {} planned, {} remaining",
+                    fmt_days(a.used),
+                    fmt_days(a.planned),
+                    fmt_days(a.remaining())
+                ),
+                Ok(None) => "no vacation policy configured".to_string(),
+                Err(e) => e.to_string(),
+            }),
         ]),
         Row::new([
             Span::styled("Time Spent On Issue: ", legend_style),
@@ -387,6 +537,20 @@ fn bottom_of_rect(r: Rect, height: u16) -> Rect {
     }
 }
 
+fn render_prompt<B: Backend>(frame: &mut Frame<B>, rect: Rect, title: &str, text: Vec<Spans>) {
+    let bottom = bottom_of_rect(rect, text.len() as u16 + 2);
+    frame.render_widget(Clear, bottom);
+    frame.render_widget(
+        Paragraph::new(text).block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        ),
+        bottom,
+    );
+}
+
 fn render_info<B: Backend>(frame: &mut Frame<B>, rect: Rect, title: &str, s: &str, color: Color) {
     frame.render_widget(Clear, rect);
     frame.render_widget(
@@ -418,11 +582,30 @@ fn render_days_off<B: Backend>(
         rect,
         selected,
         new_day_off,
-        app.days_off(),
+        app.days_off()
+            .map(|(d, kind)| format!("{} {}", d.format(DATE_FMT_FULL).unwrap(), kind)),
         "days off",
     );
 }
 
+fn render_history<B: Backend>(frame: &mut Frame<B>, rect: Rect, app: &App, selected: usize) {
+    render_datelist(
+        frame,
+        rect,
+        selected,
+        &None,
+        app.history().map(|e| {
+            format!(
+                "{} {}  {}",
+                e.at.format(DATE_FMT).unwrap(),
+                e.at.format(TIME_FMT).unwrap(),
+                e.action.describe()
+            )
+        }),
+        "history",
+    );
+}
+
 fn render_holidays<B: Backend>(
     frame: &mut Frame<B>,
     rect: Rect,
@@ -435,17 +618,110 @@ fn render_holidays<B: Backend>(
         rect,
         selected,
         new_holiday,
-        app.holidays(),
+        app.holidays().map(|d| d.format(DATE_FMT_FULL).unwrap()),
         "holidays",
     );
 }
 
-fn render_datelist<'a, B: Backend>(
+fn render_issues<B: Backend>(
+    frame: &mut Frame<B>,
+    rect: Rect,
+    app: &App,
+    selected: usize,
+    sort: IssueColumn,
+    descending: bool,
+) {
+    let smaller = Rect {
+        x: rect.x + 5,
+        y: rect.y + 5,
+        width: rect.width.saturating_sub(10),
+        height: rect.height.saturating_sub(10),
+    };
+    frame.render_widget(Clear, smaller);
+    let issues = app.issues();
+    let total = issues.iter().map(|i| i.total).sum::<Duration>();
+    let header = IssueColumn::ALL
+        .into_iter()
+        .map(|c| {
+            if c != sort {
+                c.title().to_string()
+            } else if descending {
+                format!("{} ▼", c.title())
+            } else {
+                format!("{} ▲", c.title())
+            }
+        })
+        .chain(["%".to_string()]);
+    let rows = issues.iter().map(|i| {
+        Row::new([
+            i.issue.clone(),
+            fmt_duration(i.total),
+            i.sessions.to_string(),
+            i.first_day.format(DATE_FMT).unwrap(),
+            i.last_day.format(DATE_FMT).unwrap(),
+            if total.is_positive() {
+                format!("{:.1}", i.total / total * 100.0)
+            } else {
+                "0".to_string()
+            },
+        ])
+    });
+    let table = Table::new(rows)
+        .header(Row::new(header).style(Style::default().add_modifier(Modifier::BOLD)))
+        .block(Block::default().borders(Borders::ALL).title("Issues"))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ")
+        .widths(&[
+            Constraint::Percentage(100),
+            Constraint::Length(8),
+            Constraint::Length(10),
+            Constraint::Length(11),
+            Constraint::Length(11),
+            Constraint::Length(5),
+        ]);
+    frame.render_stateful_widget(table, smaller, &mut {
+        let mut state = TableState::default();
+        state.select(Some(selected));
+        state
+    });
+}
+
+fn render_help<B: Backend>(
+    frame: &mut Frame<B>,
+    rect: Rect,
+    title: &str,
+    lines: &[(String, &str)],
+    selected: usize,
+) {
+    let smaller = Rect {
+        x: rect.x + 5,
+        y: rect.y + 3,
+        width: rect.width.saturating_sub(10),
+        height: rect.height.saturating_sub(6),
+    };
+    frame.render_widget(Clear, smaller);
+    let width = lines.iter().map(|(keys, _)| keys.len()).max().unwrap_or(0) as u16;
+    let rows = lines
+        .iter()
+        .map(|(keys, what)| Row::new([keys.clone(), what.to_string()]));
+    let widths = [Constraint::Length(width + 2), Constraint::Percentage(100)];
+    let table = Table::new(rows)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .widths(&widths);
+    frame.render_stateful_widget(table, smaller, &mut {
+        let mut state = TableState::default();
+        state.select(Some(selected));
+        state
+    });
+}
+
+fn render_datelist<B: Backend>(
     frame: &mut Frame<B>,
     rect: Rect,
     selected: usize,
     new_field: &Option<String>,
-    datelist: impl Iterator<Item = &'a time::Date>,
+    datelist: impl Iterator<Item = String>,
     title: &str,
 ) {
     let smaller = Rect {
@@ -455,20 +731,15 @@ fn render_datelist<'a, B: Backend>(
         height: rect.height.saturating_sub(10),
     };
     frame.render_widget(Clear, smaller);
-    let items = List::new(
-        datelist
-            .map(|d| d.format(DATE_FMT_FULL).unwrap())
-            .map(ListItem::new)
-            .collect::<Vec<_>>(),
-    )
-    .block(Block::default().borders(Borders::ALL).title(title))
-    .highlight_style(
-        Style::default()
-            // .bg(Color::LightGreen)
-            // .fg(Color::Black)
-            .add_modifier(Modifier::BOLD),
-    )
-    .highlight_symbol("> ");
+    let items = List::new(datelist.map(ListItem::new).collect::<Vec<_>>())
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(
+            Style::default()
+                // .bg(Color::LightGreen)
+                // .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
 
     frame.render_stateful_widget(items, smaller, &mut {
         let mut state = ListState::default();
@@ -481,9 +752,11 @@ fn render_datelist<'a, B: Backend>(
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints(
-                repeat(Constraint::Length(new_date_sizes::WIDGET_HEIGHT))
-                    .take(new_act_sizes::NUM_WIDGETS.into())
-                    .collect::<Vec<_>>(),
+                repeat_n(
+                    Constraint::Length(new_date_sizes::WIDGET_HEIGHT),
+                    new_act_sizes::NUM_WIDGETS.into(),
+                )
+                .collect::<Vec<_>>(),
             )
             .split(bottom);
         frame.render_widget(Clear, bottom);
@@ -1,4 +1,16 @@
-use time::{Duration, Weekday};
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, OnceLock},
+};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use time::{
+    format_description::FormatItem, macros::format_description, Duration, OffsetDateTime,
+    PrimitiveDateTime, UtcOffset, Weekday,
+};
+use tz::TimeZone;
 
 pub mod time_fmt {
     use time::{format_description::FormatItem, macros::format_description};
@@ -25,3 +37,223 @@ pub fn fmt_duration(d: Duration) -> String {
         d.whole_minutes().saturating_sub(d.whole_hours() * 60)
     )
 }
+
+/// Like [`fmt_duration`] but always signed, for balances that can go either way.
+pub fn fmt_balance(d: Duration) -> String {
+    let sign = if d.is_negative() { '-' } else { '+' };
+    format!("{}{}", sign, fmt_duration(d.abs()))
+}
+
+/// A number of days rounded to two decimal places, accruals produce fractions of days.
+pub fn fmt_days(days: f32) -> String {
+    ((days * 100.0).round() / 100.0).to_string()
+}
+
+/// A time zone, either one from the system's time zone database like `Europe/Paris`, whose offset
+/// changes with daylight saving time, or a fixed offset like `+01:00`.
+#[derive(Debug, Clone)]
+pub struct Zone {
+    /// The zone as it was given, `None` for the system's zone.
+    name: Option<String>,
+    tz: Arc<TimeZone>,
+}
+
+impl Zone {
+    /// The system's time zone, the one in `TZ` if it's set, UTC if it can't be determined.
+    pub fn system() -> Self {
+        static SYSTEM: OnceLock<Arc<TimeZone>> = OnceLock::new();
+        let tz = SYSTEM.get_or_init(|| {
+            let tz = match std::env::var("TZ") {
+                Ok(tz) => TimeZone::from_posix_tz(&tz),
+                Err(_) => TimeZone::local(),
+            };
+            Arc::new(tz.unwrap_or_else(|_| TimeZone::utc()))
+        });
+        Self {
+            name: None,
+            tz: tz.clone(),
+        }
+    }
+
+    pub fn fixed(offset: UtcOffset) -> Self {
+        Self {
+            name: Some(fmt_offset(offset)),
+            tz: Arc::new(TimeZone::fixed(offset.whole_seconds()).unwrap()),
+        }
+    }
+
+    /// Parses offsets like [`parse_offset`] or the names of the time zone database.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        if let Ok(offset) = parse_offset(s) {
+            return Ok(Self::fixed(offset));
+        }
+        let tz =
+            TimeZone::from_posix_tz(s).map_err(|e| format!("unknown time zone '{}': {}", s, e))?;
+        Ok(Self {
+            name: Some(s.to_string()),
+            tz: Arc::new(tz),
+        })
+    }
+
+    pub fn is_system(&self) -> bool {
+        self.name.is_none()
+    }
+
+    /// The offset from UTC of the wall clock in this zone at `at`.
+    pub fn offset_at(&self, at: OffsetDateTime) -> UtcOffset {
+        self.tz
+            .find_local_time_type(at.unix_timestamp())
+            .ok()
+            .and_then(|t| UtcOffset::from_whole_seconds(t.ut_offset()).ok())
+            .unwrap_or(UtcOffset::UTC)
+    }
+
+    /// When the wall clock in this zone shows `dt`. Around a change of the clocks, where wall
+    /// clock times are skipped or repeated, the offset from one side of the change is used.
+    pub fn to_utc(&self, dt: PrimitiveDateTime) -> OffsetDateTime {
+        let guess = self.offset_at(dt.assume_utc());
+        dt.assume_offset(self.offset_at(dt.assume_offset(guess)))
+    }
+
+    /// What the wall clock in this zone shows at `at`.
+    pub fn wall_clock(&self, at: OffsetDateTime) -> PrimitiveDateTime {
+        let at = at.to_offset(self.offset_at(at));
+        PrimitiveDateTime::new(at.date(), at.time())
+    }
+
+    /// The current wall clock time in this zone.
+    pub fn now(&self) -> PrimitiveDateTime {
+        self.wall_clock(OffsetDateTime::now_utc())
+    }
+
+    /// The offset of the system's time zone when it isn't this zone's right now, which is the case
+    /// while travelling away from it.
+    pub fn away_offset(&self) -> Option<UtcOffset> {
+        let now = OffsetDateTime::now_utc();
+        Some(Self::system().offset_at(now)).filter(|o| *o != self.offset_at(now))
+    }
+}
+
+impl Default for Zone {
+    fn default() -> Self {
+        Self::system()
+    }
+}
+
+impl PartialEq for Zone {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+/// Stored as it was given, the system's zone is stored as an empty string.
+impl Serialize for Zone {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(self.name.as_deref().unwrap_or_default())
+    }
+}
+
+impl<'de> Deserialize<'de> for Zone {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        if s.is_empty() {
+            Ok(Self::system())
+        } else {
+            Self::parse(&s).map_err(de::Error::custom)
+        }
+    }
+}
+
+/// The current wall clock time in the system's time zone.
+pub fn now() -> PrimitiveDateTime {
+    Zone::system().now()
+}
+
+/// The wall clock time in `to` when it's `dt` in `from`.
+pub fn convert(dt: PrimitiveDateTime, from: &Zone, to: &Zone) -> PrimitiveDateTime {
+    to.wall_clock(from.to_utc(dt))
+}
+
+const OFFSET_FMT: &[FormatItem<'static>] =
+    format_description!("[offset_hour sign:mandatory]:[offset_minute]");
+
+pub fn fmt_offset(offset: UtcOffset) -> String {
+    offset.format(OFFSET_FMT).unwrap()
+}
+
+/// Parses offsets like `+05:30`, `-3` or `UTC`.
+pub fn parse_offset(s: &str) -> Result<UtcOffset, &'static str> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("utc") || s.eq_ignore_ascii_case("z") {
+        return Ok(UtcOffset::UTC);
+    }
+    let s = s
+        .strip_prefix("UTC")
+        .or_else(|| s.strip_prefix("utc"))
+        .unwrap_or(s);
+    let (sign, rest) = match s.chars().next() {
+        Some('+') => (1, &s[1..]),
+        Some('-') => (-1, &s[1..]),
+        _ => return Err("offsets start with + or -, like +01:00"),
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let hours = hours.parse::<i8>().map_err(|_| "invalid offset hours")?;
+    let minutes = minutes
+        .parse::<i8>()
+        .map_err(|_| "invalid offset minutes")?;
+    UtcOffset::from_hms(sign * hours, sign * minutes, 0).map_err(|_| "offset out of range")
+}
+
+/// Stores an optional offset as text like `+05:30`, empty when there is none.
+pub mod offset_fmt {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use time::UtcOffset;
+
+    pub fn serialize<S: Serializer>(offset: &Option<UtcOffset>, s: S) -> Result<S::Ok, S::Error> {
+        match offset {
+            Some(o) => s.serialize_str(&super::fmt_offset(*o)),
+            None => s.serialize_str(""),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<UtcOffset>, D::Error> {
+        let s = String::deserialize(d)?;
+        if s.is_empty() {
+            Ok(None)
+        } else {
+            super::parse_offset(&s).map(Some).map_err(de::Error::custom)
+        }
+    }
+}
+
+/// Write a file by writing a temporary file next to it and renaming it over the original, so a
+/// crash or a full disk never leaves it half written.
+pub fn write_atomically<P: AsRef<Path>>(path: P, contents: &[u8]) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut tmp_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file"))?
+        .to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+    let written = File::create(&tmp)
+        .and_then(|mut f| {
+            f.write_all(contents)?;
+            f.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+        return written;
+    }
+    // the rename is only durable once the directory is synced
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}